@@ -0,0 +1,93 @@
+//! Generations per second of the sparse stepper and HashLife against the naive full scan.
+//!
+//! Run with `cargo bench --bench step`.
+
+use std::time::{Duration, Instant};
+
+use electric_rust::{
+    components::{cell_state::CellState, position::Position},
+    simulation::{grid::Grid, hashlife::HashLife},
+};
+
+const SIZE: i32 = 1024;
+const EXPONENT: u8 = 6;
+const GENERATIONS: u32 = 1 << EXPONENT;
+
+/// Fills the map with rows of long loops, each carrying an electron every 6 cells.
+fn loops() -> Grid {
+    let mut grid = Grid::new();
+    for y0 in (0..SIZE - 3).step_by(4) {
+        for x in 1..SIZE - 1 {
+            grid.set(Position { x, y: y0 }, CellState::Conductor);
+            grid.set(Position { x, y: y0 + 2 }, CellState::Conductor);
+        }
+        grid.set(Position { x: 1, y: y0 + 1 }, CellState::Conductor);
+        grid.set(
+            Position {
+                x: SIZE - 2,
+                y: y0 + 1,
+            },
+            CellState::Conductor,
+        );
+        for x in (3..SIZE - 3).step_by(6) {
+            grid.set(Position { x, y: y0 }, CellState::Head);
+            grid.set(Position { x: x - 1, y: y0 }, CellState::Tail);
+            grid.set(Position { x, y: y0 + 2 }, CellState::Head);
+            grid.set(
+                Position {
+                    x: x + 1,
+                    y: y0 + 2,
+                },
+                CellState::Tail,
+            );
+        }
+    }
+    grid.take_changes();
+    grid
+}
+
+fn run(name: &str, step: fn(&mut Grid)) -> Grid {
+    let mut grid = loops();
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        step(&mut grid);
+        grid.take_changes();
+    }
+    report(name, start.elapsed());
+    grid
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:>8}: {} generations in {:>8.2?} ({:.1} gen/s)",
+        name,
+        GENERATIONS,
+        elapsed,
+        GENERATIONS as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn sorted_cells(grid: &Grid) -> Vec<(Position, CellState)> {
+    let mut cells: Vec<_> = grid.cells().collect();
+    cells.sort_by_key(|(p, _)| (p.y, p.x));
+    cells
+}
+
+fn main() {
+    let naive = run("naive", Grid::step_naive);
+    let sparse = run("sparse", Grid::step);
+
+    let mut hashlife = loops();
+    let start = Instant::now();
+    HashLife::new().jump(&mut hashlife, EXPONENT);
+    report("hashlife", start.elapsed());
+
+    assert!(
+        sorted_cells(&naive) == sorted_cells(&sparse),
+        "sparse stepping diverged from the naive stepper"
+    );
+    assert!(
+        sorted_cells(&naive) == sorted_cells(&hashlife),
+        "HashLife diverged from the naive stepper"
+    );
+}
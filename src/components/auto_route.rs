@@ -0,0 +1,15 @@
+use bevy::prelude::Component;
+
+use super::position::Position;
+
+/// A wire routed automatically from a picked start cell to the cursor.
+#[derive(Component, Default)]
+pub struct AutoRoute {
+    pub active: bool,
+    /// The picked start, `None` until the first click.
+    pub start: Option<Position>,
+    /// The cell the current route leads to.
+    pub end: Position,
+    /// Cells of the route to `end`, empty when there is none.
+    pub cells: Vec<Position>,
+}
@@ -1,15 +1,26 @@
 use bevy::prelude::Component;
 
-#[derive(Component, Eq, PartialEq, Debug, Clone, Copy)]
-pub enum CellState {
-    Conductor,
-    Tail,
-    Head,
-    Empty,
+/// State of a single cell, numbered the way Golly numbers them. What a number means depends
+/// on the [`Rule`](crate::simulation::rule::Rule) of the world, 0 is always empty and 1 is
+/// always the state neighbours count, a head in Wireworld and a live cell in Life.
+#[derive(Component, Eq, PartialEq, Hash, Debug, Clone, Copy, Default)]
+pub struct CellState(pub u8);
+
+impl CellState {
+    pub const Empty: CellState = CellState(0);
+
+    // Wireworld
+    pub const Head: CellState = CellState(1);
+    pub const Tail: CellState = CellState(2);
+    pub const Conductor: CellState = CellState(3);
+
+    // Life-like and Generations rules, the dying states follow the live one
+    pub const Alive: CellState = CellState(1);
+    pub const Dying: CellState = CellState(2);
 }
 
 #[derive(Component)]
 pub struct NextState(pub CellState);
 
 #[derive(Component)]
-pub struct CurrentState(pub CellState);
\ No newline at end of file
+pub struct CurrentState(pub CellState);
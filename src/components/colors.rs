@@ -1,5 +1,9 @@
 use bevy::prelude::Color;
 
+use crate::simulation::rule::Rule;
+
+use super::cell_state::CellState;
+
 pub struct Colors;
 
 impl Colors {
@@ -7,6 +11,9 @@ impl Colors {
     pub const Tail: Color = Color::rgba(36. / 255., 148. / 255., 209. / 255., 1.);
     pub const Head: Color = Color::rgba(17. / 255., 64. / 255., 194. / 255., 1.);
 
+    pub const Alive: Color = Color::rgba(240. / 255., 220. / 255., 90. / 255., 1.);
+    pub const Dying: Color = Color::rgba(200. / 255., 60. / 255., 70. / 255., 1.);
+
     pub const ConductorSelector: Color =
         Color::rgba(0.42352941176, 0.96078431372, 0.25882352941, 1.0);
     pub const EnergizeSelector: Color =
@@ -24,4 +31,57 @@ impl Colors {
         Color::rgba(0.25882352941, 0.28235294117, 0.96078431372, 0.4);
 
     pub const SelectionOverlay: Color = Color::rgba(0.8, 0.8, 0.8, 0.4);
+    pub const WorldEdge: Color = Color::rgba(0.8, 0.8, 0.8, 0.6);
+    pub const Probe: Color = Color::rgba(0.9, 0.3, 0.9, 0.5);
+    pub const Breakpoint: Color = Color::rgba(0.95, 0.1, 0.1, 0.5);
+    pub const BreakpointArea: Color = Color::rgba(0.95, 0.1, 0.1, 0.2);
+    pub const BreakpointHit: Color = Color::rgba(1.0, 0.9, 0.1, 0.7);
+    pub const Cold: Color = Color::rgba(0.12, 0.14, 0.3, 1.0);
+    pub const Hot: Color = Color::rgba(1.0, 0.4, 0.1, 1.0);
+
+    /// Color of a cell in `state` under `rule`. Dying cells of Generations rules darken as they
+    /// get closer to empty.
+    pub fn of(rule: Rule, state: CellState) -> Color {
+        match rule {
+            Rule::Wireworld => match state {
+                CellState::Conductor => Colors::Conductor,
+                CellState::Tail => Colors::Tail,
+                CellState::Head => Colors::Head,
+                _ => Color::NONE,
+            },
+            Rule::Generations { states, .. } => match state {
+                CellState::Empty => Color::NONE,
+                CellState::Alive => Colors::Alive,
+                CellState(dying) => {
+                    let fade = 1. - (dying - 2) as f32 / (states - 1) as f32;
+                    let c = Colors::Dying;
+                    Color::rgba(c.r() * fade, c.g() * fade, c.b() * fade, 1.)
+                }
+            },
+        }
+    }
+
+    /// `color` tinted from blue for cold cells to orange for hot ones, `heat` going from 0 to 1.
+    pub fn heat(color: Color, heat: f32) -> Color {
+        let (cold, hot) = (Colors::Cold, Colors::Hot);
+        let tint = |c: f32, h: f32, base: f32| {
+            let tint = c + (h - c) * heat;
+            base + (tint - base) * 0.75
+        };
+        Color::rgba(
+            tint(cold.r(), hot.r(), color.r()),
+            tint(cold.g(), hot.g(), color.g()),
+            tint(cold.b(), hot.b(), color.b()),
+            color.a(),
+        )
+    }
+
+    /// Half transparent color of a cell, for previews of cells not placed yet.
+    pub fn ghost(rule: Rule, state: CellState) -> Color {
+        let mut color = Colors::of(rule, state);
+        if color != Color::NONE {
+            color.set_a(0.5);
+        }
+        color
+    }
 }
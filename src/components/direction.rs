@@ -2,7 +2,9 @@ use std::ops::{Add, Neg, Sub};
 
 use bevy::{math::Vec2, prelude::Component};
 
-#[derive(Component, Copy, Clone)]
+use super::position::Position;
+
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Direction(usize);
 
 impl Into<Vec2> for Direction {
@@ -21,6 +23,16 @@ impl Into<Vec2> for Direction {
     }
 }
 
+impl From<Direction> for Position {
+    fn from(d: Direction) -> Self {
+        let v = d.vec();
+        Position {
+            x: v.x as i32,
+            y: v.y as i32,
+        }
+    }
+}
+
 impl Into<usize> for Direction {
     fn into(self) -> usize {
         self.0
@@ -63,7 +75,7 @@ impl Neg for Direction {
     type Output = Direction;
 
     fn neg(self) -> Self::Output {
-        Direction((self.0 + 2) % 4)
+        self.rotate(4)
     }
 }
 
@@ -91,4 +103,14 @@ impl Direction {
     pub fn vec(self) -> Vec2 {
         self.into()
     }
+
+    /// Turns clockwise by `steps` eighths of a full turn, negative steps turn counter clockwise.
+    pub fn rotate(self, steps: i32) -> Direction {
+        Direction((self.0 as i32 + steps).rem_euclid(8) as usize)
+    }
+
+    /// Clockwise quarter turns from north, rounded down for diagonals.
+    pub fn quarter_turns(self) -> i32 {
+        self.0 as i32 / 2
+    }
 }
@@ -1,22 +1,201 @@
+use std::fmt::{self, Display};
+
 use bevy::prelude::Component;
 
-use super::{position::Position, area::Area};
+use super::position::Position;
 
 #[derive(Component)]
 pub struct LineAssist {
-    pub area: Area,
+    /// Cells of the line being drawn, from the origin to the snapped cursor.
+    pub cells: Vec<Position>,
     pub drawing: bool,
     pub origin: Position,
+    pub mode: LineMode,
 }
 
 impl Default for LineAssist {
     fn default() -> Self {
         LineAssist {
-            area: Default::default(),
+            cells: Vec::new(),
             drawing: false,
             origin: Default::default(),
+            mode: Default::default(),
+        }
+    }
+}
+
+/// How the end of a line follows the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineMode {
+    /// Horizontal or vertical, whichever is closer.
+    #[default]
+    Axis,
+    /// Horizontal, vertical or at 45 degrees.
+    Diagonal,
+    /// Straight to the cursor at any angle.
+    Free,
+}
+
+impl LineMode {
+    pub fn next(&self) -> LineMode {
+        match self {
+            LineMode::Axis => LineMode::Diagonal,
+            LineMode::Diagonal => LineMode::Free,
+            LineMode::Free => LineMode::Axis,
+        }
+    }
+
+    /// Where a line from `a` towards `b` ends.
+    pub fn snap(&self, a: Position, b: Position) -> Position {
+        let d = b - a;
+        let (dx, dy) = (d.x.abs(), d.y.abs());
+        match self {
+            LineMode::Free => b,
+            LineMode::Axis if dx > dy => Position { x: b.x, y: a.y },
+            LineMode::Axis => Position { x: a.x, y: b.y },
+            // Snap to the closest of the eight directions, tan(22.5°) is about 2/5
+            LineMode::Diagonal if dy * 5 < dx * 2 => Position { x: b.x, y: a.y },
+            LineMode::Diagonal if dx * 5 < dy * 2 => Position { x: a.x, y: b.y },
+            LineMode::Diagonal => {
+                let n = dx.max(dy);
+                a + Position {
+                    x: d.x.signum() * n,
+                    y: d.y.signum() * n,
+                }
+            }
         }
     }
+
+    /// Cells of the line from `a` towards `b`, both ends included.
+    pub fn line(&self, a: Position, b: Position) -> Vec<Position> {
+        bresenham(a, self.snap(a, b))
+    }
 }
 
+impl Display for LineMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineMode::Axis => write!(f, "straight"),
+            LineMode::Diagonal => write!(f, "straight or diagonal"),
+            LineMode::Free => write!(f, "free"),
+        }
+    }
+}
 
+/// Cells of the line from `a` to `b`, each touching the previous one at a side or a corner.
+pub fn bresenham(a: Position, b: Position) -> Vec<Position> {
+    let dx = (b.x - a.x).abs();
+    let dy = -(b.y - a.y).abs();
+    let sx = (b.x - a.x).signum();
+    let sy = (b.y - a.y).signum();
+    let mut error = dx + dy;
+    let mut p = a;
+    let mut cells = Vec::with_capacity(dx.max(-dy) as usize + 1);
+    loop {
+        cells.push(p);
+        if p == b {
+            return cells;
+        }
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            p.x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            p.y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p((x, y): (i32, i32)) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn axis_snaps_to_the_closer_axis() {
+        let a = p((2, -1));
+        for (to, snapped) in [
+            ((5, 1), (5, 0)),
+            ((1, 5), (0, 5)),
+            ((-5, 2), (-5, 0)),
+            ((-2, 6), (0, 6)),
+            ((-3, -4), (0, -4)),
+            ((6, -5), (6, 0)),
+            ((0, 0), (0, 0)),
+        ] {
+            assert_eq!(
+                LineMode::Axis.snap(a, a + p(to)),
+                a + p(snapped),
+                "{:?}",
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn diagonal_snaps_to_the_closest_of_eight_directions() {
+        let a = p((2, -1));
+        for (to, snapped) in [
+            // Within 22.5° of an axis
+            ((10, 3), (10, 0)),
+            ((-10, 2), (-10, 0)),
+            ((3, -9), (0, -9)),
+            ((-1, 10), (0, 10)),
+            // Closer to a diagonal, in each quadrant
+            ((10, 5), (10, 10)),
+            ((-10, 6), (-10, 10)),
+            ((-7, -8), (-8, -8)),
+            ((9, -8), (9, -9)),
+            ((4, -10), (10, -10)),
+        ] {
+            assert_eq!(
+                LineMode::Diagonal.snap(a, a + p(to)),
+                a + p(snapped),
+                "{:?}",
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn free_lines_go_to_the_cursor() {
+        let (a, b) = (p((2, -1)), p((-7, 3)));
+        assert_eq!(LineMode::Free.snap(a, b), b);
+    }
+
+    #[test]
+    fn lines_have_no_gaps() {
+        let a = p((1, 2));
+        for to in [
+            (0, 0),
+            (7, 0),
+            (0, -7),
+            (5, 5),
+            (-5, 5),
+            (7, 3),
+            (-7, -3),
+            (3, -7),
+            (-2, 9),
+        ] {
+            let b = a + p(to);
+            let cells = bresenham(a, b);
+            assert_eq!(cells.first(), Some(&a), "{:?}", to);
+            assert_eq!(cells.last(), Some(&b), "{:?}", to);
+            // Each cell one step from the one before, so no cell is missed or repeated
+            assert!(
+                cells.windows(2).all(|w| {
+                    let step = (w[1] - w[0]).abs();
+                    step.x.max(step.y) == 1
+                }),
+                "{:?}",
+                cells
+            );
+            assert_eq!(cells.len() as i32, to.0.abs().max(to.1.abs()) + 1);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 pub mod area;
+pub mod auto_route;
 pub mod cell_state;
 pub mod colors;
 pub mod direction;
 pub mod position;
 pub mod selection;
 pub mod line_assist;
+pub mod path_tool;
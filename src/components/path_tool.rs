@@ -0,0 +1,72 @@
+use bevy::prelude::Component;
+
+use super::{line_assist::bresenham, position::Position};
+
+/// A wire drawn through several corners, applied as a whole once finished.
+#[derive(Component, Default)]
+pub struct PathTool {
+    pub active: bool,
+    /// Corners placed so far, each segment between them already snapped to the line mode.
+    pub corners: Vec<Position>,
+    /// Cells of the path through the corners and on to the cursor, for the preview.
+    pub cells: Vec<Position>,
+    /// When the last corner was placed, in seconds since startup, to catch double clicks.
+    pub last_click: f64,
+}
+
+impl PathTool {
+    /// Cells of the path through `corners`, each cell once per segment.
+    pub fn cells_through(corners: &[Position]) -> Vec<Position> {
+        let mut cells = Vec::new();
+        for (i, corner) in corners.iter().enumerate() {
+            match corners.get(i + 1) {
+                // The end of a segment is the start of the next one
+                Some(next) => cells.extend(bresenham(*corner, *next).split_last().unwrap().1),
+                None => cells.push(*corner),
+            }
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_run_through_every_corner_once() {
+        let corners: Vec<Position> = [(0, 0), (6, 0), (6, 4), (2, 8), (-3, 8)]
+            .into_iter()
+            .map(Position::from)
+            .collect();
+        let cells = PathTool::cells_through(&corners);
+
+        assert_eq!(cells.first(), corners.first());
+        assert_eq!(cells.last(), corners.last());
+        // Each cell one step from the one before, none repeated where segments join
+        assert!(
+            cells.windows(2).all(|w| {
+                let step = (w[1] - w[0]).abs();
+                step.x.max(step.y) == 1
+            }),
+            "{:?}",
+            cells
+        );
+        for corner in corners.iter() {
+            assert_eq!(
+                cells.iter().filter(|c| *c == corner).count(),
+                1,
+                "{:?}",
+                corner
+            );
+        }
+        assert_eq!(cells.len(), 6 + 4 + 4 + 5 + 1);
+    }
+
+    #[test]
+    fn a_single_corner_is_a_single_cell() {
+        let corner = Position::from((3, -2));
+        assert_eq!(PathTool::cells_through(&[corner]), vec![corner]);
+        assert!(PathTool::cells_through(&[]).is_empty());
+    }
+}
@@ -1,9 +1,9 @@
-use std::ops::Sub;
+use std::ops::{Add, Sub};
 
 use bevy::{math::Vec2, prelude::Component};
 use bevy_ecs_tilemap::tiles::TilePos;
 
-#[derive(Component, Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Component, Debug, Eq, PartialEq, Hash, Copy, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -30,6 +30,14 @@ impl Position {
             y: self.y.abs(),
         }
     }
+
+    /// Rotates a quarter turn clockwise around the origin.
+    pub fn rotate_cw(&self) -> Self {
+        Self {
+            x: self.y,
+            y: -self.x,
+        }
+    }
 }
 
 impl Default for Position {
@@ -38,6 +46,17 @@ impl Default for Position {
     }
 }
 
+impl Add<Position> for Position {
+    type Output = Position;
+
+    fn add(self, rhs: Position) -> Self::Output {
+        Position {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
 impl Sub<Position> for Position {
     type Output = Position;
 
@@ -10,6 +10,14 @@ pub struct Selection {
     pub origin: Position,
 }
 
+impl Selection {
+    /// The selected cells. The far corner of `area` lies just outside of the selection, this
+    /// area includes both of its corners.
+    pub fn cells(&self) -> Area {
+        Area::new(self.area.a, self.area.b - Position::from((1, 1)))
+    }
+}
+
 impl Default for Selection {
     fn default() -> Self {
         Self {
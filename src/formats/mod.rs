@@ -0,0 +1,3 @@
+pub mod rle;
+pub mod save;
+pub mod vcd;
@@ -0,0 +1,455 @@
+//! Golly's run length encoded pattern format.
+//!
+//! ```text
+//! #C A diode
+//! x = 6, y = 3, rule = WireWorld
+//! .2C$2C.3C$.2C!
+//! ```
+//!
+//! Two state rules write `b` for empty and `o` for alive. Rules with more states write `.` for
+//! empty and `A` to `X` for states 1 to 24, with a prefix from `p` to `y` for the states after
+//! that, so Wireworld has `A` for head, `B` for tail and `C` for conductor.
+//! Rows run from top to bottom, so the first row ends up at the highest y.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Write},
+};
+
+use crate::{
+    components::{cell_state::CellState, position::Position},
+    simulation::{pattern::Pattern, rule::Rule},
+};
+
+pub const EXTENSION: &str = "rle";
+
+const LINE_LENGTH: usize = 70;
+
+/// Widest and highest pattern read.
+pub const MAX_SIZE: i32 = 1 << 14;
+/// Most cells of a pattern read.
+pub const MAX_CELLS: usize = 1 << 22;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RleError {
+    MissingHeader,
+    InvalidHeader(String),
+    UnsupportedRule(String),
+    UnknownState(char),
+    Unterminated,
+    /// Larger than [`MAX_SIZE`] on a side or with more than [`MAX_CELLS`] cells.
+    TooLarge,
+}
+
+impl Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RleError::MissingHeader => write!(f, "missing 'x = .., y = ..' header"),
+            RleError::InvalidHeader(h) => write!(f, "invalid header '{}'", h),
+            RleError::UnsupportedRule(r) => write!(f, "rule '{}' is not supported", r),
+            RleError::UnknownState(c) => write!(f, "unknown cell state '{}'", c),
+            RleError::Unterminated => write!(f, "pattern does not end with '!'"),
+            RleError::TooLarge => write!(f, "pattern is too large"),
+        }
+    }
+}
+
+impl Error for RleError {}
+
+/// Reads a pattern, along with the rule it was written for if the header names one.
+pub fn parse(text: &str) -> Result<(Pattern, Option<Rule>), RleError> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    let header = lines.next().ok_or(RleError::MissingHeader)?;
+    let (width, height, rule) = parse_header(header)?;
+
+    // Cells are collected top row first, and flipped once the height is known
+    let mut cells = Vec::new();
+    let mut x = 0;
+    let mut y = 0;
+    let mut rows = 0;
+    let mut count: Option<i32> = None;
+    let mut prefix: Option<u8> = None;
+    let mut terminated = false;
+
+    'data: for line in lines {
+        for c in line.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                let run = count
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|run| run.checked_add(digit as i32))
+                    .filter(|run| *run <= MAX_SIZE)
+                    .ok_or(RleError::TooLarge)?;
+                count = Some(run);
+                continue;
+            }
+            if let 'p'..='y' = c {
+                prefix = Some(c as u8 - b'p' + 1);
+                continue;
+            }
+            let run = count.take().unwrap_or(1);
+            let high = prefix.take();
+            let state = match c {
+                '!' => {
+                    terminated = true;
+                    break 'data;
+                }
+                '$' => {
+                    x = 0;
+                    y += run;
+                    if y >= MAX_SIZE {
+                        return Err(RleError::TooLarge);
+                    }
+                    rows = rows.max(y + 1);
+                    continue;
+                }
+                '.' | 'b' => CellState::Empty,
+                'o' => CellState::Alive,
+                'A'..='X' => {
+                    let state = high.unwrap_or(0) as u32 * 24 + (c as u32 - 'A' as u32) + 1;
+                    CellState(u8::try_from(state).map_err(|_| RleError::UnknownState(c))?)
+                }
+                c if c.is_whitespace() => continue,
+                c => return Err(RleError::UnknownState(c)),
+            };
+            if x + run > MAX_SIZE {
+                return Err(RleError::TooLarge);
+            }
+            if state != CellState::Empty {
+                if cells.len() + run as usize > MAX_CELLS {
+                    return Err(RleError::TooLarge);
+                }
+                for i in 0..run {
+                    cells.push((Position { x: x + i, y }, state));
+                }
+            }
+            rows = rows.max(y + 1);
+            x += run;
+        }
+    }
+
+    if !terminated {
+        return Err(RleError::Unterminated);
+    }
+
+    let width = width.max(cells.iter().map(|(p, _)| p.x + 1).max().unwrap_or(0));
+    let height = height.max(rows);
+    let mut pattern = Pattern::new(width, height);
+    pattern.cells = cells
+        .into_iter()
+        .map(|(p, state)| {
+            (
+                Position {
+                    x: p.x,
+                    y: height - 1 - p.y,
+                },
+                state,
+            )
+        })
+        .collect();
+    Ok((pattern, rule))
+}
+
+fn parse_header(header: &str) -> Result<(i32, i32, Option<Rule>), RleError> {
+    // Bounded grids put a comma in the rule, as in `rule = B3/S23:T10,10`, so a field without
+    // a key continues the one before it
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for field in header.split(',') {
+        match (field.split_once('='), fields.last_mut()) {
+            (Some((key, value)), _) => fields.push((key.trim(), value.trim().to_string())),
+            (None, Some((_, value))) => {
+                value.push(',');
+                value.push_str(field.trim());
+            }
+            (None, None) => return Err(RleError::InvalidHeader(header.to_string())),
+        }
+    }
+
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+    for (key, value) in fields {
+        match key {
+            "x" => width = value.parse().ok(),
+            "y" => height = value.parse().ok(),
+            "rule" => {
+                // Patterns do not carry the world they came from, the grid size is dropped
+                let name = value.split(':').next().unwrap_or_default();
+                rule = Some(
+                    name.parse()
+                        .map_err(|_| RleError::UnsupportedRule(value.clone()))?,
+                )
+            }
+            _ => {}
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) if width > MAX_SIZE || height > MAX_SIZE => {
+            Err(RleError::TooLarge)
+        }
+        // Only empty patterns, which Golly writes as `x = 0, y = 0`, have no cells
+        (Some(width), Some(height)) if (width > 0 && height > 0) || (width, height) == (0, 0) => {
+            Ok((width, height, rule))
+        }
+        _ => Err(RleError::InvalidHeader(header.to_string())),
+    }
+}
+
+/// Writes `pattern` as a pattern of `rule`.
+pub fn write(pattern: &Pattern, rule: Rule) -> String {
+    let mut rows = vec![vec![CellState::Empty; pattern.width as usize]; pattern.height as usize];
+    for (p, state) in pattern.cells.iter() {
+        rows[(pattern.height - 1 - p.y) as usize][p.x as usize] = *state;
+    }
+
+    let two_states = rule.states() == 2;
+    let empty = token(CellState::Empty, two_states);
+
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    for row in rows.iter() {
+        let mut row_runs: Vec<(usize, String)> = Vec::new();
+        for state in row {
+            let c = token(*state, two_states);
+            match row_runs.last_mut() {
+                Some((count, last)) if *last == c => *count += 1,
+                _ => row_runs.push((1, c)),
+            }
+        }
+        if matches!(row_runs.last(), Some((_, last)) if *last == empty) {
+            row_runs.pop();
+        }
+        push_run(&mut runs, 1, "$".to_string());
+        for (count, c) in row_runs {
+            push_run(&mut runs, count, c);
+        }
+    }
+    // The loop above starts every row with a '$', the first one is not needed
+    if let Some((count, c)) = runs.first_mut() {
+        if c == "$" {
+            *count -= 1;
+        }
+    }
+    while matches!(runs.last(), Some((_, c)) if c == "$") {
+        runs.pop();
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "x = {}, y = {}, rule = {}",
+        pattern.width, pattern.height, rule
+    )
+    .unwrap();
+    let mut line = String::new();
+    for (count, c) in runs.into_iter().filter(|(count, _)| *count > 0) {
+        let token = if count == 1 {
+            c
+        } else {
+            format!("{}{}", count, c)
+        };
+        if line.len() + token.len() > LINE_LENGTH {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        line.push_str(&token);
+    }
+    line.push('!');
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
+
+/// How `state` is written, `b` and `o` for two state rules and letters for the others.
+fn token(state: CellState, two_states: bool) -> String {
+    match state {
+        CellState::Empty if two_states => "b".to_string(),
+        CellState::Empty => ".".to_string(),
+        CellState::Alive if two_states => "o".to_string(),
+        CellState(state) => {
+            let index = state as u32 - 1;
+            let letter = char::from_u32('A' as u32 + index % 24).unwrap();
+            match index / 24 {
+                0 => letter.to_string(),
+                high => format!(
+                    "{}{}",
+                    char::from_u32('p' as u32 + high - 1).unwrap(),
+                    letter
+                ),
+            }
+        }
+    }
+}
+
+fn push_run(runs: &mut Vec<(usize, String)>, count: usize, c: String) {
+    match runs.last_mut() {
+        Some((last_count, last)) if *last == c => *last_count += count,
+        _ => runs.push((count, c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut pattern: Pattern) -> Pattern {
+        pattern.cells.sort_by_key(|(p, _)| (p.y, p.x));
+        pattern
+    }
+
+    /// Every state of `rule` in a few runs, with an empty row and column in between.
+    fn sample(rule: Rule) -> Pattern {
+        let mut pattern = Pattern::new(7, 4);
+        for state in 1..rule.states() {
+            let x = state as i32 - 1;
+            pattern.cells.push((Position { x, y: 3 }, CellState(state)));
+            pattern
+                .cells
+                .push((Position { x: x + 4, y: 0 }, CellState(state)));
+        }
+        pattern.cells.push((Position { x: 6, y: 3 }, CellState(1)));
+        pattern
+    }
+
+    #[test]
+    fn round_trip_every_preset() {
+        for rule in Rule::Presets {
+            let pattern = sample(rule);
+            let text = write(&pattern, rule);
+            assert_eq!(
+                parse(&text).map(|(pattern, rule)| (sorted(pattern), rule)),
+                Ok((sorted(pattern), Some(rule))),
+                "{}\n{}",
+                rule,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_many_states() {
+        let rule = Rule::Generations {
+            birth: 1 << 2,
+            survive: 0,
+            states: 60,
+        };
+        let mut pattern = Pattern::new(59, 1);
+        for state in 1..rule.states() {
+            let p = Position {
+                x: state as i32 - 1,
+                y: 0,
+            };
+            pattern.cells.push((p, CellState(state)));
+        }
+        let text = write(&pattern, rule);
+        assert!(text.contains("XpApB"), "{}", text);
+        assert!(text.contains("pXqAqB"), "{}", text);
+        assert_eq!(parse(&text), Ok((pattern, Some(rule))));
+    }
+
+    #[test]
+    fn empty_pattern() {
+        let text = write(&Pattern::new(0, 0), Rule::Wireworld);
+        assert_eq!(
+            parse(&text),
+            Ok((Pattern::new(0, 0), Some(Rule::Wireworld)))
+        );
+        assert_eq!(parse("x = 0, y = 0\n!"), Ok((Pattern::new(0, 0), None)));
+    }
+
+    #[test]
+    fn leading_empty_rows() {
+        let (pattern, _) = parse("x = 1, y = 3, rule = WireWorld\n2$.C!").unwrap();
+        assert_eq!(pattern.width, 2);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(
+            pattern.cells,
+            vec![(Position { x: 1, y: 0 }, CellState::Conductor)]
+        );
+    }
+
+    #[test]
+    fn golly_export() {
+        let text = "#CXRLE Pos=-4,-2 Gen=0
+x = 9, y = 5, rule = WireWorld
+4.C$3.C.C$BA2C3.2C$3.C.C$4.C!
+";
+        let (pattern, rule) = parse(text).unwrap();
+        assert_eq!(rule, Some(Rule::Wireworld));
+        assert_eq!((pattern.width, pattern.height), (9, 5));
+        assert_eq!(pattern.cells.len(), 12);
+        assert!(pattern
+            .cells
+            .contains(&(Position { x: 0, y: 2 }, CellState::Tail)));
+        assert!(pattern
+            .cells
+            .contains(&(Position { x: 1, y: 2 }, CellState::Head)));
+        assert!(pattern
+            .cells
+            .contains(&(Position { x: 4, y: 4 }, CellState::Conductor)));
+    }
+
+    #[test]
+    fn bounded_grid_rules() {
+        let (pattern, rule) = parse("x = 3, y = 1, rule = B3/S23:T10,10\n3o!").unwrap();
+        assert_eq!(rule, Some(Rule::Life));
+        assert_eq!(pattern.cells.len(), 3);
+        assert_eq!(
+            parse("x = 1, y = 1, rule = B3/S23:T10,10\no!").unwrap().1,
+            parse("x = 1, y = 1, rule = B3/S23:P20,10\no!").unwrap().1,
+        );
+        assert_eq!(
+            parse("x = 1, y = 1, rule = Nope:T10,10\no!"),
+            Err(RleError::UnsupportedRule("Nope:T10,10".to_string()))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("#C only a comment"), Err(RleError::MissingHeader));
+        assert_eq!(
+            parse("x = 1\no!"),
+            Err(RleError::InvalidHeader("x = 1".to_string()))
+        );
+        assert_eq!(parse("x = 1, y = 1\nZ!"), Err(RleError::UnknownState('Z')));
+        assert_eq!(parse("x = 1, y = 1\n3o"), Err(RleError::Unterminated));
+    }
+
+    #[test]
+    fn sizes_out_of_range() {
+        for header in ["x = -1, y = 1", "x = 1, y = 0", "x = 0, y = -5"] {
+            assert_eq!(
+                parse(&format!("{}\n!", header)),
+                Err(RleError::InvalidHeader(header.to_string()))
+            );
+        }
+        assert_eq!(parse("x = 20000, y = 1\n!"), Err(RleError::TooLarge));
+        assert_eq!(
+            parse("x = 1, y = 99999999999\n!"),
+            Err(RleError::InvalidHeader(
+                "x = 1, y = 99999999999".to_string()
+            ))
+        );
+
+        // Runs past an i32, longer than a row, and rows past the largest pattern
+        assert_eq!(
+            parse("x = 1, y = 1\n99999999999o!"),
+            Err(RleError::TooLarge)
+        );
+        assert_eq!(parse("x = 1, y = 1\n16385o!"), Err(RleError::TooLarge));
+        assert_eq!(parse("x = 1, y = 1\n16000b500o!"), Err(RleError::TooLarge));
+        assert_eq!(parse("x = 1, y = 1\n16000$400$o!"), Err(RleError::TooLarge));
+        assert!(parse("x = 1, y = 1\n16384b$16382$o!").is_ok());
+
+        // Too many cells, even though every run fits
+        let full_rows = "16384o$".repeat(MAX_CELLS / MAX_SIZE as usize + 1);
+        assert_eq!(
+            parse(&format!("x = 1, y = 1\n{}!", full_rows)),
+            Err(RleError::TooLarge)
+        );
+    }
+}
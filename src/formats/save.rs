@@ -0,0 +1,442 @@
+//! The native circuit file format.
+//!
+//! A plain text file, so circuits can be diffed and fixed by hand:
+//!
+//! ```text
+//! electric-rust 3
+//! rule WireWorld
+//! topology moore torus -128 -128 256 256
+//! generation 1200
+//! camera 52.0 -16.0 0.5
+//! cells 3
+//! 0 0 3
+//! 1 0 1
+//! 2 0 2
+//! ```
+//!
+//! The topology line holds the neighbourhood, the edges, and the origin and size of the world
+//! which only matter when it has edges.
+//! Every cell line holds the position and the state number, as Golly numbers the states of
+//! the rule. Empty cells are not stored.
+//!
+//! Version 2 files have no topology line and are infinite Moore worlds.
+//! Version 1 files also have no rule line, are always Wireworld and write the states as `C`
+//! for conductor, `H` for head and `T` for tail.
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use crate::{
+    components::{cell_state::CellState, position::Position},
+    simulation::{
+        rule::{Rule, RuleError},
+        topology::{Edges, Neighbourhood, Topology},
+    },
+};
+
+pub const MAGIC: &str = "electric-rust";
+pub const VERSION: u32 = 3;
+pub const EXTENSION: &str = "erc";
+
+/// Where the camera was looking when the circuit was saved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            zoom: 1.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SaveFile {
+    pub rule: Rule,
+    pub topology: Topology,
+    pub generation: u64,
+    pub view: View,
+    pub cells: Vec<(Position, CellState)>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    NotACircuit,
+    UnsupportedVersion(u32),
+    Corrupt { line: usize, reason: String },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "could not read file: {}", e),
+            LoadError::NotACircuit => write!(f, "not an Electric Rust circuit file"),
+            LoadError::UnsupportedVersion(v) => write!(
+                f,
+                "file format version {} is not supported, expected at most {}",
+                v, VERSION
+            ),
+            LoadError::Corrupt { line, reason } => {
+                write!(f, "corrupt file, line {}: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+pub fn write(mut w: impl Write, save: &SaveFile) -> io::Result<()> {
+    writeln!(w, "{} {}", MAGIC, VERSION)?;
+    writeln!(w, "rule {}", save.rule)?;
+    let t = save.topology;
+    writeln!(
+        w,
+        "topology {} {} {} {} {} {}",
+        t.neighbourhood, t.edges, t.origin.x, t.origin.y, t.width, t.height
+    )?;
+    writeln!(w, "generation {}", save.generation)?;
+    writeln!(
+        w,
+        "camera {} {} {}",
+        save.view.x, save.view.y, save.view.zoom
+    )?;
+    let cells: Vec<_> = save
+        .cells
+        .iter()
+        .filter(|(_, state)| *state != CellState::Empty)
+        .collect();
+    writeln!(w, "cells {}", cells.len())?;
+    for (p, state) in cells {
+        writeln!(w, "{} {} {}", p.x, p.y, state.0)?;
+    }
+    w.flush()
+}
+
+pub fn read(r: impl BufRead) -> Result<SaveFile, LoadError> {
+    let mut lines = Lines {
+        inner: r.lines(),
+        number: 0,
+    };
+
+    let header = lines.next()?.ok_or(LoadError::NotACircuit)?;
+    let mut header = header.split_whitespace();
+    if header.next() != Some(MAGIC) {
+        return Err(LoadError::NotACircuit);
+    }
+    let version: u32 = lines.parse(header.next(), "version")?;
+    if version > VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    let rule = if version >= 2 {
+        let rule = lines.field("rule")?;
+        let rule = rule.first().ok_or_else(|| lines.corrupt("missing rule"))?;
+        rule.parse()
+            .map_err(|e: RuleError| lines.corrupt(&e.to_string()))?
+    } else {
+        Rule::Wireworld
+    };
+
+    let topology = if version >= 3 {
+        let fields = lines.field("topology")?;
+        let field = |i: usize| fields.get(i).map(String::as_str);
+        let topology = Topology {
+            neighbourhood: Neighbourhood::All
+                .into_iter()
+                .find(|n| Some(n.to_string().as_str()) == field(0))
+                .ok_or_else(|| lines.corrupt("unknown neighbourhood"))?,
+            edges: Edges::All
+                .into_iter()
+                .find(|e| Some(e.to_string().as_str()) == field(1))
+                .ok_or_else(|| lines.corrupt("unknown edges"))?,
+            origin: Position {
+                x: lines.parse(field(2), "world x")?,
+                y: lines.parse(field(3), "world y")?,
+            },
+            width: lines.parse(field(4), "world width")?,
+            height: lines.parse(field(5), "world height")?,
+        };
+        if topology.width < 1 || topology.height < 1 {
+            return Err(lines.corrupt("world size must be positive"));
+        }
+        if !topology.fits() {
+            return Err(lines.corrupt("world is too large"));
+        }
+        topology
+    } else {
+        Topology::default()
+    };
+
+    let generation = lines.field("generation")?;
+    let generation = lines.parse(generation.first().map(String::as_str), "generation")?;
+
+    let camera = lines.field("camera")?;
+    let view = View {
+        x: lines.parse(camera.get(0).map(String::as_str), "camera x")?,
+        y: lines.parse(camera.get(1).map(String::as_str), "camera y")?,
+        zoom: lines.parse(camera.get(2).map(String::as_str), "camera zoom")?,
+    };
+    if !view.x.is_finite() || !view.y.is_finite() {
+        return Err(lines.corrupt("camera position must be finite"));
+    }
+    if !(view.zoom > 0.) || !view.zoom.is_finite() {
+        return Err(lines.corrupt("camera zoom must be positive"));
+    }
+
+    let count = lines.field("cells")?;
+    let count: usize = lines.parse(count.first().map(String::as_str), "cell count")?;
+    let mut cells = Vec::with_capacity(count.min(1 << 20));
+    for _ in 0..count {
+        let line = lines
+            .next()?
+            .ok_or_else(|| lines.corrupt("file ends before all cells were read"))?;
+        let mut fields = line.split_whitespace();
+        let x = lines.parse(fields.next(), "cell x")?;
+        let y = lines.parse(fields.next(), "cell y")?;
+        let state = match (version, fields.next()) {
+            (1, Some("C")) => CellState::Conductor,
+            (1, Some("H")) => CellState::Head,
+            (1, Some("T")) => CellState::Tail,
+            (1, Some(other)) => {
+                return Err(lines.corrupt(&format!("unknown cell state '{}'", other)))
+            }
+            (_, Some(number)) => match number.parse() {
+                Ok(state) if state > 0 && state < rule.states() => CellState(state),
+                _ => {
+                    return Err(lines.corrupt(&format!(
+                        "cell state '{}' does not exist in {}",
+                        number, rule
+                    )))
+                }
+            },
+            (_, None) => return Err(lines.corrupt("missing cell state")),
+        };
+        cells.push((Position { x, y }, state));
+    }
+
+    Ok(SaveFile {
+        rule,
+        topology,
+        generation,
+        view,
+        cells,
+    })
+}
+
+struct Lines<R> {
+    inner: io::Lines<R>,
+    number: usize,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn next(&mut self) -> Result<Option<String>, LoadError> {
+        loop {
+            match self.inner.next() {
+                None => return Ok(None),
+                Some(line) => {
+                    self.number += 1;
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        return Ok(Some(line));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a `name value...` line, returning the values.
+    fn field(&mut self, name: &str) -> Result<Vec<String>, LoadError> {
+        let line = self
+            .next()?
+            .ok_or_else(|| self.corrupt(&format!("missing '{}'", name)))?;
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some(name) {
+            return Err(self.corrupt(&format!("expected '{}'", name)));
+        }
+        Ok(fields.map(String::from).collect())
+    }
+
+    fn parse<T: FromStr>(&self, value: Option<&str>, what: &str) -> Result<T, LoadError> {
+        let value = value.ok_or_else(|| self.corrupt(&format!("missing {}", what)))?;
+        value
+            .parse()
+            .map_err(|_| self.corrupt(&format!("invalid {} '{}'", what, value)))
+    }
+
+    fn corrupt(&self, reason: &str) -> LoadError {
+        LoadError::Corrupt {
+            line: self.number,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(save: &SaveFile) -> SaveFile {
+        let mut bytes = Vec::new();
+        write(&mut bytes, save).unwrap();
+        read(bytes.as_slice()).unwrap()
+    }
+
+    fn corrupt_line(text: &str) -> usize {
+        match read(text.as_bytes()) {
+            Err(LoadError::Corrupt { line, .. }) => line,
+            other => panic!("expected a corrupt file, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn write_then_read_gives_the_same_circuit() {
+        let save = SaveFile {
+            rule: Rule::StarWars,
+            topology: Topology {
+                neighbourhood: Neighbourhood::Hexagonal,
+                edges: Edges::KleinBottle,
+                origin: Position::from((-128, -64)),
+                width: 256,
+                height: 100,
+            },
+            generation: 1200,
+            view: View {
+                x: 52.5,
+                y: -16.,
+                zoom: 0.25,
+            },
+            cells: vec![
+                (Position::from((0, 0)), CellState(1)),
+                (Position::from((-1, 5)), CellState(2)),
+                (Position::from((7, -3)), CellState(3)),
+            ],
+        };
+        assert_eq!(round_trip(&save), save);
+    }
+
+    #[test]
+    fn empty_cells_are_not_written() {
+        let save = SaveFile {
+            cells: vec![
+                (Position::from((0, 0)), CellState::Empty),
+                (Position::from((1, 0)), CellState::Conductor),
+                (Position::from((2, 0)), CellState::Empty),
+            ],
+            ..SaveFile::default()
+        };
+        let loaded = round_trip(&save);
+        assert_eq!(
+            loaded.cells,
+            vec![(Position::from((1, 0)), CellState::Conductor)]
+        );
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let text = "electric-rust 1\ngeneration 3\ncamera 0 0 1\ncells 2\n0 0 C\n1 0 H\n";
+        let save = read(text.as_bytes()).unwrap();
+        assert_eq!(save.rule, Rule::Wireworld);
+        assert_eq!(save.generation, 3);
+        assert_eq!(
+            save.cells,
+            vec![
+                (Position::from((0, 0)), CellState::Conductor),
+                (Position::from((1, 0)), CellState::Head),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(read("".as_bytes()), Err(LoadError::NotACircuit)));
+        assert!(matches!(
+            read("x = 3, y = 1\n".as_bytes()),
+            Err(LoadError::NotACircuit)
+        ));
+        assert!(matches!(
+            read("electric-rust 99\n".as_bytes()),
+            Err(LoadError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn reports_the_corrupt_line() {
+        let header = "electric-rust 3\nrule WireWorld\ntopology moore infinite 0 0 1 1\n";
+        let camera = "generation 0\ncamera 0 0 1\n";
+        // Missing version, unknown rule and edges, empty world
+        assert_eq!(corrupt_line("electric-rust\n"), 1);
+        assert_eq!(corrupt_line("electric-rust 3\nrule Nope\n"), 2);
+        assert_eq!(
+            corrupt_line("electric-rust 3\nrule WireWorld\ntopology moore round 0 0 1 1\n"),
+            3
+        );
+        assert_eq!(
+            corrupt_line("electric-rust 3\nrule WireWorld\ntopology moore torus 0 0 0 5\n"),
+            3
+        );
+        // Worlds reaching past the largest position
+        assert_eq!(
+            corrupt_line(&format!(
+                "electric-rust 3\nrule WireWorld\ntopology moore torus {} 0 5 5\n",
+                i32::MAX - 2
+            )),
+            3
+        );
+        assert_eq!(
+            corrupt_line(&format!(
+                "electric-rust 3\nrule WireWorld\ntopology moore bounded 0 {} 5 5\n",
+                i32::MIN
+            )),
+            3
+        );
+        assert_eq!(
+            corrupt_line(&format!(
+                "electric-rust 3\nrule WireWorld\ntopology moore torus 0 0 {} 5\n",
+                i32::MAX
+            )),
+            3
+        );
+        // Zero and non-finite zoom, non-finite camera position
+        for camera in ["0 0 0", "0 0 inf", "0 0 NaN", "NaN 0 1", "0 -inf 1"] {
+            assert_eq!(
+                corrupt_line(&format!("{}generation 0\ncamera {}\n", header, camera)),
+                5,
+                "{}",
+                camera
+            );
+        }
+        assert_eq!(
+            corrupt_line(&format!("{}{}cells 2\n0 0 3\n", header, camera)),
+            7
+        );
+        assert_eq!(
+            corrupt_line(&format!("{}{}cells 1\n0 0 4\n", header, camera)),
+            7
+        );
+        assert_eq!(
+            corrupt_line(&format!("{}{}cells 1\n0 0 0\n", header, camera)),
+            7
+        );
+        assert_eq!(
+            corrupt_line(&format!("{}{}cells 1\nx 0 1\n", header, camera)),
+            7
+        );
+    }
+}
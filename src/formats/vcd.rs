@@ -0,0 +1,84 @@
+//! Value change dump traces of probes, as read by waveform viewers such as GTKWave.
+//!
+//! ```text
+//! $timescale 1 ns $end
+//! $scope module world $end
+//! $var reg 2 ! p0 $end
+//! $upscope $end
+//! $enddefinitions $end
+//! #0
+//! b11 !
+//! #4
+//! b1 !
+//! ```
+//!
+//! Each probe is a register holding the number of its cell's state, for Wireworld 1 for head,
+//! 2 for tail and 3 for conductor. One generation takes one nanosecond.
+
+use std::fmt::Write;
+
+use crate::simulation::{probes::Probes, rule::Rule};
+
+pub const EXTENSION: &str = "vcd";
+
+/// Identifier of the `i`th variable, made of the printable characters `!` to `~`.
+fn identifier(mut i: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (i % 94) as u8) as char);
+        i /= 94;
+        if i == 0 {
+            return id;
+        }
+        i -= 1;
+    }
+}
+
+pub fn write(probes: &Probes, rule: Rule) -> String {
+    let states = rule.states() as u32;
+    let width = (u32::BITS - (states - 1).leading_zeros()).max(1);
+    let ids: Vec<String> = (0..probes.probes.len()).map(identifier).collect();
+
+    let mut out = String::new();
+    writeln!(out, "$comment {} $end", rule.name()).unwrap();
+    writeln!(out, "$timescale 1 ns $end").unwrap();
+    writeln!(out, "$scope module world $end").unwrap();
+    for (probe, id) in probes.probes.iter().zip(ids.iter()) {
+        writeln!(
+            out,
+            "$comment {} probes cell {}, {} $end",
+            probe.name, probe.position.x, probe.position.y
+        )
+        .unwrap();
+        writeln!(out, "$var reg {} {} {} $end", width, id, probe.name).unwrap();
+    }
+    writeln!(out, "$upscope $end").unwrap();
+    writeln!(out, "$enddefinitions $end").unwrap();
+
+    // Changes of every probe, merged in the order of their generations
+    let mut changes: Vec<(u64, usize, u8)> = Vec::new();
+    for (i, probe) in probes.probes.iter().enumerate() {
+        let mut last = None;
+        for (generation, state) in probe.samples.iter() {
+            if last != Some(state.0) {
+                changes.push((*generation, i, state.0));
+                last = Some(state.0);
+            }
+        }
+    }
+    changes.sort_unstable();
+
+    let mut time = None;
+    for (generation, i, value) in changes {
+        if time != Some(generation) {
+            writeln!(out, "#{}", generation).unwrap();
+            time = Some(generation);
+        }
+        if width == 1 {
+            writeln!(out, "{}{}", value, ids[i]).unwrap();
+        } else {
+            writeln!(out, "b{:b} {}", value, ids[i]).unwrap();
+        }
+    }
+    out
+}
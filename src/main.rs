@@ -1,28 +1,72 @@
-pub mod components;
 mod resources;
 pub mod systems;
 pub mod utils;
 
-use std::time::Duration;
+pub use electric_rust::{components, formats, simulation};
 
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use bevy::render::texture::ImageSettings;
 use bevy_ecs_tilemap::TilemapPlugin;
+use components::auto_route::AutoRoute;
 use components::line_assist::LineAssist;
+use components::path_tool::PathTool;
 use components::selection::Selection;
 use iyes_loopless::prelude::*;
 use resources::area_action_event::AreaActionEvent;
+use resources::clock_tool::ClockTool;
+use resources::library::Library;
+use resources::cells_action_event::CellsActionEvent;
 use resources::controls::Controls;
+use resources::clipboard::{Clipboard, PastePreview};
 use resources::cursor::Cursor;
+use resources::document::Document;
+use resources::file_prompt::{FileChosen, FilePrompt};
+use resources::head_count::HeadCountInput;
+use resources::jump::Jump;
+use resources::period_search::PeriodSearch;
+use resources::speed::Speed;
+use resources::status::Status;
+use resources::stepping::Stepping;
+use resources::waveform::Waveform;
+use simulation::breakpoints::Breakpoints;
+use simulation::grid::Grid;
+use simulation::hashlife::HashLife;
+use simulation::heatmap::Heatmap;
+use simulation::history::History;
+use simulation::probes::Probes;
+use simulation::rewind::Rewind;
+use systems::breakpoints::{breakpoint_shortcuts, update_breakpoint_markers};
 use systems::camera::{camera_movement, CameraZoom, MousePosition};
-use systems::input::{edit_wire, handle_input};
+use systems::clipboard::{
+    clipboard_shortcuts, place_paste, transform_shortcuts, update_paste_preview,
+};
+use systems::clock::clock_tool;
+use systems::file::{file_prompt, file_shortcuts, open_files};
+use systems::history::history_shortcuts;
+use systems::input::{auto_route, edit_wire, handle_input, path_tool};
+use systems::library::{library_shortcuts, palette_buttons, update_palette};
+use systems::probes::{
+    export_probes, probe_shortcuts, record_probes, update_probe_markers, update_waveform,
+};
 use systems::setup::setup;
-use systems::state::{do_state, update_state};
-use systems::ui::{icon_toggles, update_cursor, update_toolbar_icons};
+use systems::state::{
+    do_state, find_period, heatmap_shortcuts, jump_generations, period_shortcuts, rule_shortcuts,
+    speed_shortcuts, step_paused, step_shortcuts, sync_tiles, topology_shortcuts, update_heatmap,
+    update_state,
+};
+use systems::ui::{
+    icon_toggles, update_cursor, update_line_preview, update_stats, update_status,
+    update_toolbar_icons, update_world_edges, update_world_text,
+};
 
 pub const CELL_SIZE: f32 = 4.;
-pub const SIZE: i32 = 1024;
+/// How many edits can be undone.
+pub const UNDO_DEPTH: usize = 256;
+/// How many past generations can be stepped back to.
+pub const REWIND_GENERATIONS: usize = 1024;
+/// Upper bound on the cells stored for stepping back, across all generations.
+pub const REWIND_CELLS: usize = 1 << 22;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum AppState {
@@ -35,40 +79,110 @@ pub fn paused(app_state: Res<AppState>) -> bool {
 }
 
 fn main() {
-    let mut update_stage = SystemStage::parallel();
-    update_stage.add_system(update_state.label("update_state"));
-    update_stage.add_system(do_state.run_if_not(paused).after("update_state"));
-
     App::new()
         .add_event::<AreaActionEvent>()
+        .add_event::<CellsActionEvent>()
+        .add_event::<FileChosen>()
         .insert_resource(Msaa { samples: 4 })
         .insert_resource(ImageSettings::default_nearest())
         .add_plugins(DefaultPlugins)
         .add_plugin(TilemapPlugin)
         .insert_resource(AppState::Running)
+        .insert_resource(Grid::new())
+        .insert_resource(HashLife::new())
+        .insert_resource(History::new(UNDO_DEPTH))
+        .insert_resource(Rewind::new(REWIND_GENERATIONS, REWIND_CELLS))
+        .insert_resource(Jump::default())
+        .insert_resource(Stepping::default())
+        .insert_resource(Speed::default())
         .insert_resource(MousePosition::default())
         .insert_resource(CameraZoom(1.))
         .insert_resource(Cursor::default())
         .insert_resource(Selection::default())
         .insert_resource(LineAssist::default())
+        .insert_resource(PathTool::default())
+        .insert_resource(AutoRoute::default())
         .insert_resource(Controls::default())
+        .insert_resource(Document::default())
+        .insert_resource(FilePrompt::default())
+        .insert_resource(Clipboard::default())
+        .insert_resource(PastePreview::default())
+        .insert_resource(Status::default())
+        .insert_resource(Library::load())
+        .insert_resource(ClockTool::default())
+        .insert_resource(Probes::default())
+        .insert_resource(Breakpoints::default())
+        .insert_resource(HeadCountInput::default())
+        .insert_resource(Heatmap::default())
+        .insert_resource(PeriodSearch::default())
+        .insert_resource(Waveform::default())
         .add_startup_system(setup)
+        // Before any shortcut, which do not see the keys typed into a path
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            file_prompt.after(bevy::input::InputSystem),
+        )
         .add_system_set(
             SystemSet::new()
                 .with_system(camera_movement)
                 .with_system(handle_input.after(camera_movement).label("handle_input"))
                 .with_system(update_cursor.after(camera_movement))
+                .with_system(jump_generations)
+                .with_system(speed_shortcuts)
+                .with_system(rule_shortcuts)
+                .with_system(topology_shortcuts)
+                .with_system(heatmap_shortcuts)
+                .with_system(period_shortcuts)
+                .with_system(find_period.after(period_shortcuts))
+                .with_system(step_shortcuts)
+                .with_system(step_paused.after(step_shortcuts))
+                .with_system(file_shortcuts.after(camera_movement))
+                .with_system(open_files.after(file_shortcuts))
+                .with_system(clipboard_shortcuts.after("handle_input"))
+                .with_system(place_paste.after("handle_input").label("place_paste"))
+                .with_system(library_shortcuts)
+                .with_system(clock_tool.after("place_paste"))
+                .with_system(update_palette)
+                // After placing, so the click picking a part does not also place it
+                .with_system(palette_buttons.after("place_paste"))
+                .with_system(transform_shortcuts.after("handle_input"))
+                .with_system(history_shortcuts.after("handle_input"))
+                .with_system(update_paste_preview.after(camera_movement))
+                .with_system(update_status)
+                .with_system(update_world_text)
+                .with_system(update_stats.after(camera_movement))
                 .with_system(icon_toggles)
-                .with_system(update_toolbar_icons),
+                .with_system(path_tool.after(camera_movement).label("path_tool"))
+                .with_system(auto_route.after(camera_movement).label("auto_route"))
+                .with_system(
+                    update_line_preview
+                        .after("handle_input")
+                        .after("path_tool")
+                        .after("auto_route"),
+                )
+                .with_system(probe_shortcuts.after(camera_movement))
+                .with_system(export_probes.after(probe_shortcuts))
+                .with_system(update_probe_markers)
+                .with_system(breakpoint_shortcuts.after(camera_movement))
+                .with_system(update_breakpoint_markers)
+                .with_system(update_toolbar_icons)
+                .with_system(update_world_edges),
         )
-        .add_stage(
-            "fixed_update",
-            FixedTimestepStage::new(Duration::from_millis(125)).with_stage(update_stage),
+        .add_stage_after(
+            CoreStage::Update,
+            "simulation",
+            SystemStage::parallel().with_system(do_state.run_if_not(paused)),
         )
         .add_stage_after(
-            "fixed_update",
+            "simulation",
             "edit_wire",
-            SystemStage::parallel().with_system(edit_wire),
+            SystemStage::parallel()
+                .with_system(edit_wire.label("edit_wire"))
+                .with_system(sync_tiles.label("sync_tiles").after("edit_wire"))
+                .with_system(update_state.after("sync_tiles").label("update_state"))
+                .with_system(update_heatmap.after("update_state"))
+                .with_system(record_probes.after("edit_wire"))
+                .with_system(update_waveform.after(record_probes)),
         )
         .add_plugin(LogDiagnosticsPlugin::default())
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
@@ -0,0 +1,13 @@
+use bevy::prelude::Component;
+
+use crate::components::position::Position;
+
+use super::area_action_event::AreaAction;
+
+/// Like an [`AreaActionEvent`](super::area_action_event::AreaActionEvent), for cells that do
+/// not make up a rectangle. All cells are undone together.
+#[derive(Component)]
+pub struct CellsActionEvent {
+    pub cells: Vec<Position>,
+    pub action: AreaAction,
+}
@@ -0,0 +1,24 @@
+use std::collections::HashMap;
+
+use bevy::prelude::{Entity, Handle, Image};
+
+use crate::components::position::Position;
+
+/// Tilemap entities rendering each chunk of the grid, spawned the first time a cell in the
+/// chunk changes. Hexagonal layouts have one per row of a chunk, keyed by the chunk column and
+/// the row.
+pub struct ChunkMaps {
+    pub texture: Handle<Image>,
+    pub maps: HashMap<Position, Entity>,
+    pub hexagonal: bool,
+}
+
+impl ChunkMaps {
+    pub fn new(texture: Handle<Image>) -> Self {
+        Self {
+            texture,
+            maps: HashMap::new(),
+            hexagonal: false,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::simulation::pattern::Pattern;
+
+/// Cells copied or cut from the grid.
+#[derive(Default)]
+pub struct Clipboard {
+    pub pattern: Option<Pattern>,
+}
+
+/// A pattern following the cursor, stamped into the grid on the next click.
+#[derive(Default)]
+pub struct PastePreview {
+    pub pattern: Option<Pattern>,
+    // Set after stamping, so the same click does not go on to draw with the current mode
+    pub wait_for_release: bool,
+}
+
+impl PastePreview {
+    pub fn active(&self) -> bool {
+        self.pattern.is_some() || self.wait_for_release
+    }
+}
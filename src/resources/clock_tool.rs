@@ -0,0 +1,28 @@
+/// Settings of the clock being placed, typed in while the clock tool is active.
+pub struct ClockTool {
+    pub active: bool,
+    pub period: u32,
+    pub phase: u32,
+    /// Which of the two numbers typing changes.
+    pub editing: ClockField,
+    /// Digits typed so far, applied once they make a valid number.
+    pub typed: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockField {
+    Period,
+    Phase,
+}
+
+impl Default for ClockTool {
+    fn default() -> Self {
+        Self {
+            active: false,
+            period: 8,
+            phase: 0,
+            editing: ClockField::Period,
+            typed: String::new(),
+        }
+    }
+}
@@ -1,14 +1,19 @@
-use bevy::prelude::{Component, KeyCode, MouseButton};
+use bevy::prelude::{Component, Input, KeyCode, MouseButton};
+
+use crate::simulation::fill::Connectivity;
 
 #[derive(Component)]
 pub struct Controls {
     pub mode: ControlMode,
+    /// Which cells the fill tool treats as touching.
+    pub connectivity: Connectivity,
 }
 
 impl Default for Controls {
     fn default() -> Self {
         Self {
             mode: ControlMode::Move,
+            connectivity: Connectivity::default(),
         }
     }
 }
@@ -26,6 +31,25 @@ impl Controls {
     pub const PauseKey: KeyCode = KeyCode::Space;
     pub const SelectArea: KeyCode = KeyCode::LControl;
     pub const DrawLine: KeyCode = KeyCode::LShift;
+    pub const CycleLineMode: KeyCode = KeyCode::L;
+    pub const PathTool: KeyCode = KeyCode::P;
+    pub const FinishPath: KeyCode = KeyCode::Return;
+    pub const RemoveCorner: KeyCode = KeyCode::Back;
+    // While typing a path, without native file dialogs
+    pub const ChoosePath: KeyCode = KeyCode::Return;
+    pub const ErasePath: KeyCode = KeyCode::Back;
+    pub const AutoRoute: KeyCode = KeyCode::A;
+    pub const ClockTool: KeyCode = KeyCode::K;
+    pub const ClockNextField: KeyCode = KeyCode::Tab;
+    pub const ClockErase: KeyCode = KeyCode::Back;
+    pub const ToggleProbe: KeyCode = KeyCode::O;
+    pub const WaveformZoomIn: KeyCode = KeyCode::X;
+    pub const WaveformZoomOut: KeyCode = KeyCode::Z;
+    // Shift sets the head count breakpoint, ctrl removes all breakpoints
+    pub const Breakpoint: KeyCode = KeyCode::U;
+    pub const SetHeadCount: KeyCode = KeyCode::Return;
+    pub const HeadCountErase: KeyCode = KeyCode::Back;
+    pub const Heatmap: KeyCode = KeyCode::Q;
     pub const SpeedKey: KeyCode = KeyCode::LShift;
     pub const MoveUp: KeyCode = KeyCode::PageUp;
     pub const MoveRight: KeyCode = KeyCode::Right;
@@ -33,11 +57,50 @@ impl Controls {
     pub const MoveLeft: KeyCode = KeyCode::Left;
     pub const Unselect: KeyCode = KeyCode::Escape;
     pub const FillSelection: KeyCode = KeyCode::F;
+    pub const ToggleConnectivity: KeyCode = KeyCode::G;
     pub const Cancel: KeyCode = KeyCode::Escape;
+    // Only while paused, shift advances by the advance amount
+    pub const Step: KeyCode = KeyCode::N;
+    pub const StepBack: KeyCode = KeyCode::B;
+    pub const AdvanceMore: KeyCode = KeyCode::Period;
+    pub const AdvanceLess: KeyCode = KeyCode::Comma;
+    pub const Rotate: KeyCode = KeyCode::R;
+    pub const FlipHorizontal: KeyCode = KeyCode::H;
+    pub const FlipVertical: KeyCode = KeyCode::V;
+    pub const Faster: KeyCode = KeyCode::Equals;
+    pub const Slower: KeyCode = KeyCode::Minus;
+    pub const Jump: KeyCode = KeyCode::J;
+    pub const JumpFurther: KeyCode = KeyCode::RBracket;
+    pub const JumpShorter: KeyCode = KeyCode::LBracket;
+
+    // Used together with ctrl, shift selects the "as" variant
+    pub const Save: KeyCode = KeyCode::S;
+    pub const Open: KeyCode = KeyCode::O;
+    pub const ImportPattern: KeyCode = KeyCode::I;
+    pub const ExportPattern: KeyCode = KeyCode::E;
+    pub const Copy: KeyCode = KeyCode::C;
+    pub const Cut: KeyCode = KeyCode::X;
+    pub const Paste: KeyCode = KeyCode::V;
+    pub const CycleRule: KeyCode = KeyCode::R;
+    pub const CycleNeighbourhood: KeyCode = KeyCode::H;
+    pub const CycleEdges: KeyCode = KeyCode::B;
+    pub const Undo: KeyCode = KeyCode::Z;
+    pub const Redo: KeyCode = KeyCode::Y;
+    pub const AddToLibrary: KeyCode = KeyCode::L;
+    pub const ExportProbes: KeyCode = KeyCode::P;
+    pub const FindPeriod: KeyCode = KeyCode::F;
 
     pub const MouseMove: MouseButton = MouseButton::Middle;
     pub const MousePrimary: MouseButton = MouseButton::Left;
     pub const MouseSecondary: MouseButton = MouseButton::Right;
+
+    pub fn ctrl(keys: &Input<KeyCode>) -> bool {
+        keys.any_pressed([KeyCode::LControl, KeyCode::RControl])
+    }
+
+    pub fn shift(keys: &Input<KeyCode>) -> bool {
+        keys.any_pressed([KeyCode::LShift, KeyCode::RShift])
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
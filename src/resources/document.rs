@@ -0,0 +1,7 @@
+use std::path::PathBuf;
+
+/// The file the circuit was last saved to or opened from.
+#[derive(Default)]
+pub struct Document {
+    pub path: Option<PathBuf>,
+}
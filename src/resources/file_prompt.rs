@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use crate::formats::{rle, save, vcd};
+
+/// What a file is asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    Save,
+    Open,
+    ImportPattern,
+    ExportPattern,
+    ExportProbes,
+}
+
+impl FileAction {
+    /// Whether the file is written, rather than read.
+    pub fn writes(&self) -> bool {
+        !matches!(self, FileAction::Open | FileAction::ImportPattern)
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            FileAction::Save => "Save circuit as",
+            FileAction::Open => "Open circuit",
+            FileAction::ImportPattern => "Import pattern",
+            FileAction::ExportPattern => "Export selection as",
+            FileAction::ExportProbes => "Export probes as",
+        }
+    }
+
+    /// Name and extension of the files it takes.
+    pub fn filter(&self) -> (&'static str, &'static str) {
+        match self {
+            FileAction::Save | FileAction::Open => ("Electric Rust circuit", save::EXTENSION),
+            FileAction::ImportPattern | FileAction::ExportPattern => {
+                ("Golly RLE pattern", rle::EXTENSION)
+            }
+            FileAction::ExportProbes => ("Value change dump", vcd::EXTENSION),
+        }
+    }
+
+    /// The file name offered when writing.
+    pub fn file_name(&self) -> String {
+        let name = match self {
+            FileAction::Save | FileAction::Open => "circuit",
+            FileAction::ImportPattern | FileAction::ExportPattern => "pattern",
+            FileAction::ExportProbes => "probes",
+        };
+        format!("{}.{}", name, self.filter().1)
+    }
+}
+
+/// The path being typed, without native file dialogs.
+#[derive(Default)]
+pub struct FilePrompt {
+    pub action: Option<FileAction>,
+    pub typed: String,
+}
+
+/// Sent once a file was picked, by a dialog or by typing its path.
+pub struct FileChosen {
+    pub action: FileAction,
+    pub path: PathBuf,
+}
@@ -0,0 +1,7 @@
+/// The count of heads typed in for the head count breakpoint.
+#[derive(Default)]
+pub struct HeadCountInput {
+    pub active: bool,
+    /// Digits typed so far.
+    pub typed: String,
+}
@@ -0,0 +1,14 @@
+/// How far the HashLife jump advances the grid, as a power of two.
+pub struct Jump {
+    pub exponent: u8,
+}
+
+impl Jump {
+    pub const MaxExponent: u8 = 48;
+}
+
+impl Default for Jump {
+    fn default() -> Self {
+        Self { exponent: 10 }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    formats::rle,
+    simulation::{
+        pattern::Pattern,
+        prefab::{Prefab, MIN_PERIOD},
+        rule::Rule,
+    },
+};
+
+/// Where parts saved from selections are kept, one RLE file each, named after the part.
+pub const DIRECTORY: &str = "prefabs";
+
+/// A part of the palette.
+pub enum LibraryEntry {
+    Builtin(Prefab),
+    Saved {
+        name: String,
+        pattern: Pattern,
+        rule: Option<Rule>,
+    },
+}
+
+impl LibraryEntry {
+    pub fn name(&self) -> String {
+        match self {
+            LibraryEntry::Builtin(prefab) => prefab.to_string(),
+            LibraryEntry::Saved { name, .. } => name.clone(),
+        }
+    }
+
+    /// The rule the part was made for, `None` if it does not say.
+    pub fn rule(&self) -> Option<Rule> {
+        match self {
+            LibraryEntry::Builtin(_) => Some(Rule::Wireworld),
+            LibraryEntry::Saved { rule, .. } => *rule,
+        }
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        match self {
+            LibraryEntry::Builtin(prefab) => prefab.pattern(),
+            LibraryEntry::Saved { pattern, .. } => pattern.clone(),
+        }
+    }
+}
+
+/// Parts offered by the palette: the built in prefabs followed by the ones saved by the user.
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+}
+
+impl Library {
+    /// The built in parts and every part saved in [`DIRECTORY`].
+    pub fn load() -> Self {
+        let mut entries: Vec<LibraryEntry> = Prefab::Builtin
+            .iter()
+            .map(|prefab| LibraryEntry::Builtin(*prefab))
+            .collect();
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(DIRECTORY)
+            .map(|dir| dir.filter_map(|entry| Some(entry.ok()?.path())).collect())
+            .unwrap_or_default();
+        paths.sort();
+        for path in paths {
+            if path.extension().map_or(true, |ext| ext != rle::EXTENSION) {
+                continue;
+            }
+            match load_entry(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("Skipping part {}: {}", path.display(), e),
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Changes the period of the clocks in the palette by `by`.
+    pub fn change_clock_period(&mut self, by: i32) {
+        for entry in self.entries.iter_mut() {
+            if let LibraryEntry::Builtin(Prefab::Clock(period)) = entry {
+                *period = (*period as i32 + by).max(MIN_PERIOD as i32) as u32;
+            }
+        }
+    }
+
+    /// Saves `pattern` as a new part, named after the first free "Part N".
+    pub fn add(&mut self, pattern: Pattern, rule: Rule) -> std::io::Result<String> {
+        fs::create_dir_all(DIRECTORY)?;
+        let (name, path) = (1..)
+            .map(|i| {
+                let name = format!("Part {}", i);
+                let path = Path::new(DIRECTORY).join(format!("{}.{}", name, rle::EXTENSION));
+                (name, path)
+            })
+            .find(|(_, path)| !path.exists())
+            .unwrap();
+        fs::write(&path, rle::write(&pattern, rule))?;
+
+        self.entries.push(LibraryEntry::Saved {
+            name: name.clone(),
+            pattern,
+            rule: Some(rule),
+        });
+        Ok(name)
+    }
+}
+
+fn load_entry(path: &Path) -> Result<LibraryEntry, Box<dyn std::error::Error>> {
+    let (pattern, rule) = rle::parse(&fs::read_to_string(path)?)?;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(LibraryEntry::Saved {
+        name,
+        pattern,
+        rule,
+    })
+}
@@ -1,5 +1,19 @@
 pub mod controls;
 pub mod area_action_event;
+pub mod cells_action_event;
+pub mod chunk_maps;
+pub mod clipboard;
+pub mod clock_tool;
 pub mod cursor;
 pub mod cell_action;
+pub mod document;
 pub mod gui;
+pub mod head_count;
+pub mod jump;
+pub mod library;
+pub mod period_search;
+pub mod file_prompt;
+pub mod speed;
+pub mod status;
+pub mod stepping;
+pub mod waveform;
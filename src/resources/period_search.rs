@@ -0,0 +1,7 @@
+use crate::simulation::period::Search;
+
+/// The period search started on the selection, run a few generations every frame.
+#[derive(Default)]
+pub struct PeriodSearch {
+    pub search: Option<Search>,
+}
@@ -0,0 +1,50 @@
+/// Generations per second for each speed level, the level past the end runs uncapped.
+pub const SPEEDS: [f64; 14] = [
+    1., 2., 4., 8., 15., 30., 60., 120., 250., 500., 1000., 2500., 5000., 10000.,
+];
+
+/// How fast the simulation runs while not paused.
+pub struct Speed {
+    pub level: usize,
+    /// Fraction of a generation carried over to the next frame.
+    pub accumulator: f64,
+    /// Generations per second actually reached, smoothed over a few frames.
+    pub measured: f64,
+    /// Heads simulated per second, summed over the generations run and smoothed like
+    /// `measured`.
+    pub measured_heads: f64,
+}
+
+impl Speed {
+    pub const Uncapped: usize = SPEEDS.len();
+
+    pub fn is_uncapped(&self) -> bool {
+        self.level >= Self::Uncapped
+    }
+
+    /// Target generations per second, `None` when uncapped.
+    pub fn target(&self) -> Option<f64> {
+        SPEEDS.get(self.level).copied()
+    }
+
+    pub fn faster(&mut self) {
+        self.level = (self.level + 1).min(Self::Uncapped);
+    }
+
+    pub fn slower(&mut self) {
+        self.level = self.level.saturating_sub(1);
+        self.accumulator = 0.;
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self {
+            // 8 generations per second
+            level: 3,
+            accumulator: 0.,
+            measured: 0.,
+            measured_heads: 0.,
+        }
+    }
+}
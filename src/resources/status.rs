@@ -0,0 +1,18 @@
+/// Last message shown in the status line of the UI.
+#[derive(Default)]
+pub struct Status {
+    pub message: String,
+    pub is_error: bool,
+}
+
+impl Status {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+        self.is_error = false;
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+        self.is_error = true;
+    }
+}
@@ -0,0 +1,18 @@
+/// Generations requested while paused, stepped through as the frame budget allows.
+pub struct Stepping {
+    pub pending: u64,
+    pub advance_by: u64,
+}
+
+impl Stepping {
+    pub const MaxAdvance: u64 = 1_000_000_000;
+}
+
+impl Default for Stepping {
+    fn default() -> Self {
+        Self {
+            pending: 0,
+            advance_by: 100,
+        }
+    }
+}
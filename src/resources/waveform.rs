@@ -0,0 +1,31 @@
+/// How the waveform panel of the probes draws the generation axis.
+pub struct Waveform {
+    /// Width of a generation in pixels.
+    pub zoom: f32,
+}
+
+impl Waveform {
+    pub const MinZoom: f32 = 1. / 16.;
+    pub const MaxZoom: f32 = 32.;
+    /// Width of the traces in pixels.
+    pub const Width: f32 = 640.;
+
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom * 2.).min(Waveform::MaxZoom);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = (self.zoom / 2.).max(Waveform::MinZoom);
+    }
+
+    /// How many generations fit across the panel.
+    pub fn visible(&self) -> u64 {
+        (Waveform::Width / self.zoom) as u64
+    }
+}
+
+impl Default for Waveform {
+    fn default() -> Self {
+        Self { zoom: 4. }
+    }
+}
@@ -0,0 +1,188 @@
+//! Conditions that pause the simulation, to find the generation where a circuit misbehaves.
+
+use std::fmt::{self, Display};
+
+use crate::components::{area::Area, cell_state::CellState, position::Position};
+
+use super::grid::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// The cell turns into a head, or comes alive under other rules.
+    BecomesHead(Position),
+    /// Any cell of the area changes.
+    AreaChanges(Area),
+    /// The number of heads, or live cells under other rules, goes from at most this to more.
+    HeadsAbove(u64),
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::BecomesHead(p) => write!(f, "cell {}, {} becomes a head", p.x, p.y),
+            Breakpoint::AreaChanges(area) => write!(
+                f,
+                "area {}, {} to {}, {} changes",
+                area.a.x, area.a.y, area.b.x, area.b.y
+            ),
+            Breakpoint::HeadsAbove(n) => write!(f, "the heads go above {}", n),
+        }
+    }
+}
+
+/// A breakpoint that fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub breakpoint: Breakpoint,
+    /// The cell that set it off, `None` for head counts.
+    pub cell: Option<Position>,
+    pub generation: u64,
+}
+
+#[derive(Default)]
+pub struct Breakpoints {
+    pub breakpoints: Vec<Breakpoint>,
+    /// The breakpoint that fired in the last generation stepped, if any.
+    pub hit: Option<Hit>,
+    /// The generation last checked and how many heads it had, to tell when the count goes up.
+    heads: Option<(u64, u64)>,
+}
+
+impl Breakpoints {
+    /// Adds `breakpoint`, or removes it when already set. Returns whether it was added.
+    pub fn toggle(&mut self, breakpoint: Breakpoint) -> bool {
+        if let Some(i) = self.breakpoints.iter().position(|b| *b == breakpoint) {
+            self.breakpoints.remove(i);
+            return false;
+        }
+        self.breakpoints.push(breakpoint);
+        true
+    }
+
+    /// Looks for a breakpoint set off by the cells at `changed` in the current generation of
+    /// `grid`, keeping it as the [`Breakpoints::hit`].
+    pub fn check(&mut self, grid: &Grid, changed: &[Position]) -> Option<Hit> {
+        let generation = grid.generation();
+        let heads = grid.count(CellState::Head);
+        // Unknown after stepping back or jumping, which does not count as going up
+        let previous = match self.heads.replace((generation, heads)) {
+            Some((g, previous)) if g + 1 == generation => Some(previous),
+            _ => None,
+        };
+        let hit = |breakpoint: Breakpoint, cell: Option<Position>| Hit {
+            breakpoint,
+            cell,
+            generation,
+        };
+
+        let mut found = None;
+        for breakpoint in self.breakpoints.iter() {
+            found = match *breakpoint {
+                Breakpoint::BecomesHead(p) => changed
+                    .iter()
+                    .find(|c| **c == p && grid.get(p) == CellState::Head)
+                    .map(|c| hit(*breakpoint, Some(*c))),
+                Breakpoint::AreaChanges(area) => changed
+                    .iter()
+                    .find(|c| area.contains(**c))
+                    .map(|c| hit(*breakpoint, Some(*c))),
+                Breakpoint::HeadsAbove(n) => {
+                    if heads > n && previous.map_or(false, |previous| previous <= n) {
+                        Some(hit(*breakpoint, None))
+                    } else {
+                        None
+                    }
+                }
+            };
+            if found.is_some() {
+                break;
+            }
+        }
+
+        self.hit = found;
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A wire from 0, 0 to 9, 0 with the first `heads` cells being heads.
+    fn wire(heads: i32) -> Grid {
+        let mut grid = Grid::new();
+        for x in 0..10 {
+            let state = if x < heads {
+                CellState::Head
+            } else {
+                CellState::Conductor
+            };
+            grid.set(Position::from((x, 0)), state);
+        }
+        grid
+    }
+
+    fn step(grid: &mut Grid, breakpoints: &mut Breakpoints) -> Option<Hit> {
+        let seen = grid.changes().len();
+        grid.step();
+        let changed = grid.changes()[seen..].to_vec();
+        breakpoints.check(grid, &changed)
+    }
+
+    #[test]
+    fn head_count_fires_when_going_above() {
+        let mut grid = wire(0);
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(Breakpoint::HeadsAbove(2));
+        assert_eq!(step(&mut grid, &mut breakpoints), None);
+
+        // 3 signals running east, staying 3 heads for a few generations
+        for x in [0, 3, 6] {
+            grid.set(Position::from((x, 0)), CellState::Tail);
+            grid.set(Position::from((x + 1, 0)), CellState::Head);
+        }
+        let hit = step(&mut grid, &mut breakpoints).unwrap();
+        assert_eq!(hit.breakpoint, Breakpoint::HeadsAbove(2));
+        assert_eq!(hit.generation, 2);
+        assert!(grid.count(CellState::Head) > 2);
+        assert_eq!(step(&mut grid, &mut breakpoints), None);
+        assert!(grid.count(CellState::Head) > 2);
+        assert_eq!(breakpoints.hit, None);
+    }
+
+    #[test]
+    fn head_count_does_not_fire_at_the_limit_or_after_jumps() {
+        let mut grid = wire(0);
+        grid.set(Position::from((0, 0)), CellState::Head);
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(Breakpoint::HeadsAbove(1));
+        for _ in 0..12 {
+            assert_eq!(step(&mut grid, &mut breakpoints), None);
+        }
+
+        // Skipping generations, the count before is not known
+        let mut grid = wire(3);
+        grid.set_generation(100);
+        assert_eq!(breakpoints.check(&grid, &[]), None);
+    }
+
+    #[test]
+    fn cells_fire_when_they_change() {
+        let mut grid = wire(1);
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(Breakpoint::BecomesHead(Position::from((3, 0))));
+        breakpoints.toggle(Breakpoint::AreaChanges(Area::new(
+            Position::from((6, 0)),
+            Position::from((7, 0)),
+        )));
+        for generation in 1..=8 {
+            let hit = step(&mut grid, &mut breakpoints);
+            let cell = hit.and_then(|hit| hit.cell);
+            match generation {
+                3 => assert_eq!(cell, Some(Position::from((3, 0)))),
+                6..=8 => assert_eq!(cell, Some(Position::from((6, 0)))),
+                _ => assert_eq!(hit, None, "generation {}", generation),
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Flood fill, finding the cells connected to a starting cell.
+
+use std::fmt::{self, Display};
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::{
+    grid::{Grid, PositionSet},
+    topology::{Neighbourhood, Topology},
+};
+
+/// Most cells a fill can reach, regions starting on an empty cell of an infinite world have no
+/// end unless they are enclosed.
+pub const MAX_FILL: usize = 1 << 20;
+
+/// Which cells touch for the purpose of filling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Connectivity {
+    /// Cells sharing a side.
+    #[default]
+    Four,
+    /// Cells sharing a side or a corner.
+    Eight,
+}
+
+impl Connectivity {
+    pub fn next(&self) -> Connectivity {
+        match self {
+            Connectivity::Four => Connectivity::Eight,
+            Connectivity::Eight => Connectivity::Four,
+        }
+    }
+
+    fn neighbourhood(&self) -> Neighbourhood {
+        match self {
+            Connectivity::Four => Neighbourhood::VonNeumann,
+            Connectivity::Eight => Neighbourhood::Moore,
+        }
+    }
+}
+
+impl Display for Connectivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Connectivity::Four => write!(f, "4-connected"),
+            Connectivity::Eight => write!(f, "8-connected"),
+        }
+    }
+}
+
+/// The region connected to `start`: the non-empty cells touching it when `start` is a cell, or
+/// the empty cells around it when it is empty. Regions wrap around the edges of the world.
+///
+/// Returns `None` when the region grows past [`MAX_FILL`] cells.
+pub fn region(grid: &Grid, start: Position, connectivity: Connectivity) -> Option<Vec<Position>> {
+    let topology = Topology {
+        neighbourhood: connectivity.neighbourhood(),
+        ..grid.topology()
+    };
+    if !topology.contains(start) {
+        return Some(Vec::new());
+    }
+    let empty = grid.get(start) == CellState::Empty;
+
+    let mut seen = PositionSet::default();
+    let mut region = vec![start];
+    seen.insert(start);
+    let mut i = 0;
+    while i < region.len() {
+        for n in topology.neighbours(region[i]) {
+            if (grid.get(n) == CellState::Empty) == empty && seen.insert(n) {
+                if region.len() == MAX_FILL {
+                    return None;
+                }
+                region.push(n);
+            }
+        }
+        i += 1;
+    }
+    Some(region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::topology::Edges;
+
+    fn sorted(mut region: Vec<Position>) -> Vec<Position> {
+        region.sort_by_key(|p| (p.y, p.x));
+        region
+    }
+
+    fn world(edges: Edges, width: i32, height: i32) -> Grid {
+        let mut grid = Grid::new();
+        grid.set_topology(Topology {
+            edges,
+            origin: Position::from((0, 0)),
+            width,
+            height,
+            ..Topology::default()
+        });
+        grid
+    }
+
+    #[test]
+    fn wires_stop_at_empty_cells() {
+        let mut grid = Grid::new();
+        for x in 0..4 {
+            grid.set(Position::from((x, 0)), CellState::Conductor);
+        }
+        grid.set(Position::from((1, 0)), CellState::Head);
+        grid.set(Position::from((2, 0)), CellState::Tail);
+        // Only touching the wire at a corner
+        grid.set(Position::from((4, 1)), CellState::Conductor);
+        // Apart from it
+        grid.set(Position::from((6, 0)), CellState::Conductor);
+
+        let four = region(&grid, Position::from((0, 0)), Connectivity::Four).unwrap();
+        assert_eq!(
+            sorted(four),
+            (0..4).map(|x| Position::from((x, 0))).collect::<Vec<_>>()
+        );
+        let eight = region(&grid, Position::from((0, 0)), Connectivity::Eight).unwrap();
+        assert_eq!(eight.len(), 5);
+        assert!(!eight.contains(&Position::from((6, 0))));
+    }
+
+    #[test]
+    fn empty_regions_stop_at_cells() {
+        let mut grid = Grid::new();
+        // A ring around the 3x3 empty cells from 1, 1
+        for i in 0..5 {
+            for p in [(i, 0), (i, 4), (0, i), (4, i)] {
+                grid.set(Position::from(p), CellState::Conductor);
+            }
+        }
+        grid.set(Position::from((2, 2)), CellState::Head);
+
+        let inside = region(&grid, Position::from((1, 1)), Connectivity::Four).unwrap();
+        assert_eq!(inside.len(), 8);
+        assert!(inside
+            .iter()
+            .all(|p| p.x >= 1 && p.x <= 3 && p.y >= 1 && p.y <= 3));
+    }
+
+    #[test]
+    fn regions_respect_the_edges() {
+        // Bounded worlds end at their edges
+        let grid = world(Edges::Bounded, 5, 4);
+        let all = region(&grid, Position::from((2, 2)), Connectivity::Eight).unwrap();
+        assert_eq!(all.len(), 20);
+        assert!(all.iter().all(|p| grid.topology().contains(*p)));
+        assert!(region(&grid, Position::from((5, 0)), Connectivity::Four)
+            .unwrap()
+            .is_empty());
+
+        // A wire across the edge of a torus is one region, not in a bounded world
+        for edges in [Edges::Bounded, Edges::Torus] {
+            let mut grid = world(edges, 5, 4);
+            grid.set(Position::from((0, 1)), CellState::Conductor);
+            grid.set(Position::from((4, 1)), CellState::Conductor);
+            let wire = region(&grid, Position::from((0, 1)), Connectivity::Four).unwrap();
+            let expected = if edges == Edges::Torus { 2 } else { 1 };
+            assert_eq!(wire.len(), expected, "{}", edges);
+        }
+
+        // A Klein bottle mirrors the wire crossing its top and bottom edges
+        let mut grid = world(Edges::KleinBottle, 5, 4);
+        grid.set(Position::from((1, 0)), CellState::Conductor);
+        grid.set(Position::from((3, 3)), CellState::Conductor);
+        let wire = region(&grid, Position::from((1, 0)), Connectivity::Four).unwrap();
+        assert_eq!(wire.len(), 2);
+    }
+}
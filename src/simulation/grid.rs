@@ -0,0 +1,545 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasherDefault, Hasher},
+};
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::{rule::Rule, topology::Topology};
+
+/// Width and height of a chunk, in cells.
+pub const CHUNK_SIZE: i32 = 64;
+
+/// Hashes positions with a multiply and rotate in the style of FxHash. Stepping spends most
+/// of its time in hash lookups, and the default SipHash is several times slower.
+#[derive(Default)]
+pub struct PositionHasher(u64);
+
+impl Hasher for PositionHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u32(*byte as u32);
+        }
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0 = (self.0.rotate_left(5) ^ i as u64).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+}
+
+pub type PositionMap<V> = HashMap<Position, V, BuildHasherDefault<PositionHasher>>;
+pub type PositionSet = HashSet<Position, BuildHasherDefault<PositionHasher>>;
+
+/// Cells of a chunk.
+const CHUNK_CELLS: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Chunk containing the cell at `p`.
+pub fn chunk_of(p: Position) -> Position {
+    Position {
+        x: p.x.div_euclid(CHUNK_SIZE),
+        y: p.y.div_euclid(CHUNK_SIZE),
+    }
+}
+
+/// Position of the cell at `p` relative to the corner of its chunk.
+pub fn local_of(p: Position) -> Position {
+    Position {
+        x: p.x.rem_euclid(CHUNK_SIZE),
+        y: p.y.rem_euclid(CHUNK_SIZE),
+    }
+}
+
+/// Cells of a chunk packed `bits` to a word entry, a power of two so no cell straddles words.
+struct Chunk {
+    words: Vec<u64>,
+    bits: usize,
+    population: u32,
+}
+
+impl Chunk {
+    fn new(bits: usize) -> Self {
+        Self {
+            words: vec![0; CHUNK_CELLS * bits / 64],
+            bits,
+            population: 0,
+        }
+    }
+
+    fn index(p: Position) -> usize {
+        let local = local_of(p);
+        (local.y * CHUNK_SIZE + local.x) as usize
+    }
+
+    fn read(&self, i: usize) -> CellState {
+        let per_word = 64 / self.bits;
+        let shift = (i % per_word) * self.bits;
+        let mask = (1 << self.bits) - 1;
+        CellState(((self.words[i / per_word] >> shift) & mask) as u8)
+    }
+
+    /// Writes the cell at index `i`, returning its previous state.
+    fn write(&mut self, i: usize, state: CellState) -> CellState {
+        let old = self.read(i);
+        if old == CellState::Empty && state != CellState::Empty {
+            self.population += 1;
+        } else if old != CellState::Empty && state == CellState::Empty {
+            self.population -= 1;
+        }
+        let per_word = 64 / self.bits;
+        let shift = (i % per_word) * self.bits;
+        let mask: u64 = (1 << self.bits) - 1;
+        let word = &mut self.words[i / per_word];
+        *word = (*word & !(mask << shift)) | ((state.0 as u64) << shift);
+        old
+    }
+}
+
+/// Bits needed per cell for a rule with `states` states.
+fn bits_for(states: u8) -> usize {
+    match states {
+        0..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    }
+}
+
+/// The cells of a world and the rule they follow, without any dependency on the ECS or the
+/// tilemap. The bevy systems only forward edits to it and render the cells it reports as
+/// changed.
+///
+/// The plane is unbounded, split into chunks of [`CHUNK_SIZE`] cells that are allocated when
+/// the first cell is placed in them and freed when the last one is removed.
+/// Cells are packed as few bits as the rule's states need, two for Wireworld, and stepping
+/// only looks at the cells the rule calls active and at the neighbours of live cells, since
+/// nothing else can change. The [`Topology`] decides which cells are neighbours, and for
+/// worlds with edges which cells exist at all.
+pub struct Grid {
+    rule: Rule,
+    topology: Topology,
+    chunks: PositionMap<Chunk>,
+    active: PositionSet,
+    generation: u64,
+    edits: u64,
+    changes: Vec<Position>,
+    /// Number of cells in each state, empty cells are not counted.
+    population: Vec<u64>,
+}
+
+impl Grid {
+    pub fn new() -> Self {
+        Self::with_rule(Rule::default())
+    }
+
+    pub fn with_rule(rule: Rule) -> Self {
+        Self {
+            rule,
+            topology: Topology::default(),
+            chunks: PositionMap::default(),
+            active: PositionSet::default(),
+            generation: 0,
+            edits: 0,
+            changes: Vec::new(),
+            population: vec![0; u8::MAX as usize + 1],
+        }
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Switches the world to `rule`. Cells in states the new rule does not have become the
+    /// state it places, so drawings keep their shape.
+    pub fn set_rule(&mut self, rule: Rule) {
+        let cells: Vec<(Position, CellState)> = self.cells().collect();
+        self.rule = rule;
+        self.chunks.clear();
+        self.active.clear();
+        self.population.fill(0);
+        for (p, state) in cells {
+            let state = if state.0 < rule.states() {
+                state
+            } else {
+                rule.placed()
+            };
+            self.write(p, state);
+            self.track(p, state);
+            self.changes.push(p);
+            self.edits += 1;
+        }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Changes the neighbourhood and edges of the world, removing the cells that end up outside
+    /// of it. Every remaining cell is reported as changed, as the renderer lays cells out
+    /// differently for some neighbourhoods.
+    pub fn set_topology(&mut self, topology: Topology) {
+        let cells: Vec<(Position, CellState)> = self.cells().collect();
+        self.topology = topology;
+        for (p, _) in cells {
+            if topology.contains(p) {
+                self.changes.push(p);
+            } else {
+                self.write(p, CellState::Empty);
+                self.track(p, CellState::Empty);
+                self.changes.push(p);
+            }
+        }
+        self.edits += 1;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    /// Counts the cells changed by [`Grid::set`], stepping does not count.
+    /// Lets others notice that the grid was edited since they last looked at it.
+    pub fn edits(&self) -> u64 {
+        self.edits
+    }
+
+    /// State of the cell at `p`.
+    pub fn get(&self, p: Position) -> CellState {
+        match self.chunks.get(&chunk_of(p)) {
+            Some(chunk) => chunk.read(Chunk::index(p)),
+            None => CellState::Empty,
+        }
+    }
+
+    /// Sets the cell at `p`, returning its previous state. Cells outside of a world with edges
+    /// stay empty.
+    pub fn set(&mut self, p: Position, state: CellState) -> CellState {
+        let old = self.get(p);
+        if old != state && self.topology.contains(p) {
+            self.write(p, state);
+            self.track(p, state);
+            self.changes.push(p);
+            self.edits += 1;
+        }
+        old
+    }
+
+    fn write(&mut self, p: Position, state: CellState) {
+        let key = chunk_of(p);
+        let bits = bits_for(self.rule.states());
+        let chunk = self.chunks.entry(key).or_insert_with(|| Chunk::new(bits));
+        let old = chunk.write(Chunk::index(p), state);
+        if chunk.population == 0 {
+            self.chunks.remove(&key);
+        }
+        if old != CellState::Empty {
+            self.population[old.0 as usize] -= 1;
+        }
+        if state != CellState::Empty {
+            self.population[state.0 as usize] += 1;
+        }
+    }
+
+    fn track(&mut self, p: Position, state: CellState) {
+        if self.rule.is_active(state) {
+            self.active.insert(p);
+        } else {
+            self.active.remove(&p);
+        }
+    }
+
+    /// Advances the grid by one generation.
+    pub fn step(&mut self) {
+        self.advance(None);
+    }
+
+    /// Advances the grid by one generation, appending the previous state of every cell that
+    /// changed to `previous`.
+    pub fn step_recording(&mut self, previous: &mut Vec<(Position, CellState)>) {
+        self.advance(Some(previous));
+    }
+
+    fn advance(&mut self, mut previous: Option<&mut Vec<(Position, CellState)>>) {
+        // State and live neighbours of every cell that may change, active cells included even
+        // when none of their neighbours are alive
+        let mut counts: PositionMap<(CellState, u32)> =
+            PositionMap::with_capacity_and_hasher(self.active.len() * 2, Default::default());
+        for p in self.active.iter() {
+            let state = self.get(*p);
+            counts.entry(*p).or_insert((state, 0));
+            if state != CellState::Alive {
+                continue;
+            }
+            for n in self.topology.neighbours(*p) {
+                if let Some((_, alive)) = counts.get_mut(&n) {
+                    *alive += 1;
+                    continue;
+                }
+                let state = self.get(n);
+                if self.rule.counts_neighbours(state) {
+                    counts.insert(n, (state, 1));
+                }
+            }
+        }
+
+        let mut next = Vec::new();
+        let mut active =
+            PositionSet::with_capacity_and_hasher(self.active.len(), Default::default());
+        for (p, (state, alive)) in counts {
+            let new_state = self.rule.next(state, alive);
+            if new_state != state {
+                next.push((p, state, new_state));
+            }
+            if self.rule.is_active(new_state) {
+                active.insert(p);
+            }
+        }
+
+        for (p, state, new_state) in next {
+            if let Some(previous) = previous.as_mut() {
+                previous.push((p, state));
+            }
+            self.write(p, new_state);
+            self.changes.push(p);
+        }
+
+        self.active = active;
+        self.generation += 1;
+    }
+
+    /// Advances the grid by one generation, evaluating every cell of every allocated chunk.
+    /// Much slower than [`Grid::step`], kept as the reference implementation of the rules.
+    pub fn step_naive(&mut self) {
+        // Rules that grow can bring cells to life in the chunks around the allocated ones, or
+        // anywhere in a world that wraps around
+        let mut keys: PositionSet = self.chunks.keys().copied().collect();
+        if self.rule.grows() && self.topology.is_infinite() {
+            for key in self.chunks.keys() {
+                for n in Topology::default().neighbours(*key) {
+                    keys.insert(n);
+                }
+            }
+        } else if self.rule.grows() {
+            let t = self.topology;
+            let (a, b) = (
+                chunk_of(t.origin),
+                chunk_of(t.origin + Position::from((t.width - 1, t.height - 1))),
+            );
+            for y in a.y..=b.y {
+                for x in a.x..=b.x {
+                    keys.insert(Position { x, y });
+                }
+            }
+        }
+
+        let mut next = Vec::new();
+        for key in keys {
+            let corner = Position {
+                x: key.x * CHUNK_SIZE,
+                y: key.y * CHUNK_SIZE,
+            };
+            for i in 0..CHUNK_CELLS {
+                let p = corner + Position::from((i as i32 % CHUNK_SIZE, i as i32 / CHUNK_SIZE));
+                if !self.topology.contains(p) {
+                    continue;
+                }
+                let state = self.get(p);
+                let new_state = self.rule.next(state, self.count_alive(p));
+                if new_state != state {
+                    next.push((p, new_state));
+                }
+            }
+        }
+        for (p, state) in next {
+            self.write(p, state);
+            self.track(p, state);
+            self.changes.push(p);
+        }
+        self.generation += 1;
+    }
+
+    fn count_alive(&self, p: Position) -> u32 {
+        self.topology
+            .neighbours(p)
+            .filter(|n| self.get(*n) == CellState::Alive)
+            .count() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Removes every cell, keeping the generation counter.
+    pub fn clear(&mut self) {
+        let cells: Vec<Position> = self.cells().map(|(p, _)| p).collect();
+        for p in cells {
+            self.set(p, CellState::Empty);
+        }
+    }
+
+    /// Positions changed since the last call, by edits or by stepping.
+    pub fn take_changes(&mut self) -> Vec<Position> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Positions changed since [`Grid::take_changes`] was last called, oldest first and
+    /// repeated when changed more than once.
+    pub fn changes(&self) -> &[Position] {
+        &self.changes
+    }
+
+    /// How many cells are in `state`, 0 for empty cells.
+    pub fn count(&self, state: CellState) -> u64 {
+        if state == CellState::Empty {
+            return 0;
+        }
+        self.population[state.0 as usize]
+    }
+
+    /// Every non-empty cell of the grid, in no particular order.
+    pub fn cells(&self) -> impl Iterator<Item = (Position, CellState)> + '_ {
+        self.chunks.iter().flat_map(|(key, chunk)| {
+            let corner = Position {
+                x: key.x * CHUNK_SIZE,
+                y: key.y * CHUNK_SIZE,
+            };
+            (0..CHUNK_CELLS)
+                .map(move |i| {
+                    let local = Position::from((i as i32 % CHUNK_SIZE, i as i32 / CHUNK_SIZE));
+                    (corner + local, chunk.read(i))
+                })
+                .filter(|(_, state)| *state != CellState::Empty)
+        })
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::topology::{Edges, Neighbourhood};
+
+    /// Deterministic xorshift, so failures can be reproduced.
+    fn random(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    }
+
+    fn sorted(grid: &Grid) -> Vec<(Position, CellState)> {
+        let mut cells: Vec<_> = grid.cells().collect();
+        cells.sort_by_key(|(p, _)| (p.y, p.x));
+        cells
+    }
+
+    #[test]
+    fn step_matches_step_naive() {
+        let mut seed = 999;
+        for rule in Rule::Presets {
+            for neighbourhood in Neighbourhood::All {
+                for edges in Edges::All {
+                    let topology = Topology {
+                        neighbourhood,
+                        edges,
+                        // Across the chunk borders at 0
+                        origin: Position::from((-12, -9)),
+                        width: 30,
+                        height: 20,
+                    };
+                    let mut sparse = Grid::with_rule(rule);
+                    sparse.set_topology(topology);
+                    for _ in 0..200 {
+                        let x = (random(&mut seed) % 40) as i32 - 15;
+                        let y = (random(&mut seed) % 30) as i32 - 12;
+                        let state = CellState((random(&mut seed) % rule.states() as u64) as u8);
+                        sparse.set(Position { x, y }, state);
+                    }
+                    let mut naive = Grid::with_rule(rule);
+                    naive.set_topology(topology);
+                    for (p, state) in sparse.cells() {
+                        naive.set(p, state);
+                    }
+
+                    for generation in 0..40 {
+                        sparse.step();
+                        naive.step_naive();
+                        assert_eq!(
+                            sorted(&sparse),
+                            sorted(&naive),
+                            "{} {} {} generation {}",
+                            rule,
+                            neighbourhood,
+                            edges,
+                            generation
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_and_get_across_chunk_borders() {
+        let mut grid = Grid::new();
+        let positions = [
+            (0, 0),
+            (-1, -1),
+            (-1, 0),
+            (0, -1),
+            (CHUNK_SIZE - 1, CHUNK_SIZE - 1),
+            (CHUNK_SIZE, CHUNK_SIZE),
+            (-CHUNK_SIZE, -CHUNK_SIZE),
+            (-CHUNK_SIZE - 1, CHUNK_SIZE),
+            (-1_000_000, 999_999),
+        ];
+        for (i, (x, y)) in positions.iter().enumerate() {
+            let state = CellState(i as u8 % 3 + 1);
+            assert_eq!(grid.set(Position::from((*x, *y)), state), CellState::Empty);
+        }
+        for (i, (x, y)) in positions.iter().enumerate() {
+            assert_eq!(
+                grid.get(Position::from((*x, *y))),
+                CellState(i as u8 % 3 + 1),
+                "{}, {}",
+                x,
+                y
+            );
+        }
+        assert_eq!(grid.cells().count(), positions.len());
+        assert_eq!(grid.get(Position::from((1, 1))), CellState::Empty);
+        assert_eq!(grid.get(Position::from((-2, -1))), CellState::Empty);
+    }
+
+    #[test]
+    fn empty_chunks_are_freed() {
+        let mut grid = Grid::new();
+        let a = Position::from((-1, -1));
+        let b = Position::from((-CHUNK_SIZE, -2));
+        grid.set(a, CellState::Conductor);
+        grid.set(b, CellState::Conductor);
+        grid.set(Position::from((0, 0)), CellState::Conductor);
+        assert_eq!(grid.chunks.len(), 2);
+
+        grid.set(a, CellState::Empty);
+        assert_eq!(grid.chunks.len(), 2);
+        grid.set(b, CellState::Empty);
+        assert_eq!(grid.chunks.len(), 1);
+        grid.set(Position::from((0, 0)), CellState::Empty);
+        assert!(grid.is_empty());
+    }
+}
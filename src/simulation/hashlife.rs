@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::{grid::Grid, rule::Rule, topology::Neighbourhood};
+
+type NodeId = u32;
+
+/// Caches are dropped once this many nodes exist, to keep memory bounded.
+const MAX_NODES: usize = 1 << 22;
+
+/// The jump needs more than [`MAX_NODES`] nodes even with empty caches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooBig;
+
+impl Display for TooBig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the pattern changes too much to jump that far")
+    }
+}
+
+impl Error for TooBig {}
+
+/// Corner of a node. Nodes get far larger than the cells they hold, so their corners can lie
+/// outside of what a [`Position`] can address.
+type Corner = (i64, i64);
+
+/// Children are ordered south-west, south-east, north-west, north-east,
+/// so child `i` covers the quadrant at `(i & 1, i >> 1)`.
+#[derive(Clone, Copy)]
+struct Node {
+    level: u8,
+    children: [NodeId; 4],
+    state: CellState,
+}
+
+/// Memoized quadtree engine in the style of Gosper's HashLife.
+///
+/// Identical regions share one node, and the future of every node is cached, so repetitive
+/// circuits like clocks and counters can be advanced by huge powers of two at once.
+/// Keep one instance around between jumps, the caches are what makes it fast.
+///
+/// Only works on infinite worlds, see [`HashLife::supports`].
+pub struct HashLife {
+    rule: Rule,
+    neighbourhood: Neighbourhood,
+    nodes: Vec<Node>,
+    branches: HashMap<[NodeId; 4], NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+}
+
+impl HashLife {
+    pub fn new() -> Self {
+        let mut hashlife = Self {
+            rule: Rule::default(),
+            neighbourhood: Neighbourhood::default(),
+            nodes: Vec::new(),
+            branches: HashMap::new(),
+            results: HashMap::new(),
+            empty: Vec::new(),
+        };
+        hashlife.reset();
+        hashlife
+    }
+
+    fn reset(&mut self) {
+        self.nodes.clear();
+        self.branches.clear();
+        self.results.clear();
+        // One leaf per state, numbered like the states themselves
+        for state in 0..=u8::MAX {
+            self.nodes.push(Node {
+                level: 0,
+                children: [0; 4],
+                state: CellState(state),
+            });
+        }
+        self.empty = vec![self.leaf(CellState::Empty)];
+    }
+
+    fn leaf(&self, state: CellState) -> NodeId {
+        state.0 as NodeId
+    }
+
+    fn level(&self, n: NodeId) -> u8 {
+        self.nodes[n as usize].level
+    }
+
+    fn child(&self, n: NodeId, i: usize) -> NodeId {
+        self.nodes[n as usize].children[i]
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> Result<NodeId, TooBig> {
+        if let Some(n) = self.branches.get(&children) {
+            return Ok(*n);
+        }
+        if self.nodes.len() >= MAX_NODES {
+            return Err(TooBig);
+        }
+        let n = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level: self.level(children[0]) + 1,
+            children,
+            state: CellState::Empty,
+        });
+        self.branches.insert(children, n);
+        Ok(n)
+    }
+
+    fn empty(&mut self, level: u8) -> Result<NodeId, TooBig> {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let n = self.join([e; 4])?;
+            self.empty.push(n);
+        }
+        Ok(self.empty[level as usize])
+    }
+
+    /// Builds the node of `level` whose south-west corner is `origin`, from `cells` which must
+    /// all lie inside it.
+    fn build(
+        &mut self,
+        level: u8,
+        origin: Corner,
+        cells: &[(Position, CellState)],
+    ) -> Result<NodeId, TooBig> {
+        if cells.is_empty() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return Ok(self.leaf(cells[0].1));
+        }
+        let half = 1i64 << (level - 1);
+        let mut quadrants: [Vec<(Position, CellState)>; 4] = Default::default();
+        for (p, state) in cells {
+            let x = (p.x as i64 - origin.0 >= half) as usize;
+            let y = (p.y as i64 - origin.1 >= half) as usize;
+            quadrants[y * 2 + x].push((*p, *state));
+        }
+        let mut children = [0; 4];
+        for (i, quadrant) in quadrants.iter().enumerate() {
+            children[i] = self.build(level - 1, quadrant_corner(origin, half, i), quadrant)?;
+        }
+        self.join(children)
+    }
+
+    /// Appends the non-empty cells of `n`, skipping those a [`Position`] cannot address.
+    fn collect(&self, n: NodeId, origin: Corner, cells: &mut Vec<(Position, CellState)>) {
+        let node = self.nodes[n as usize];
+        if n == self.empty[node.level as usize] {
+            return;
+        }
+        if node.level == 0 {
+            if let (Ok(x), Ok(y)) = (i32::try_from(origin.0), i32::try_from(origin.1)) {
+                cells.push((Position { x, y }, node.state));
+            }
+            return;
+        }
+        let half = 1i64 << (node.level - 1);
+        for (i, child) in node.children.iter().enumerate() {
+            self.collect(*child, quadrant_corner(origin, half, i), cells);
+        }
+    }
+
+    /// The 4x4 grandchildren of `n`, indexed `[y][x]` from the south-west corner.
+    fn grandchildren(&self, n: NodeId) -> [[NodeId; 4]; 4] {
+        let mut g = [[0; 4]; 4];
+        for (y, row) in g.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let child = self.child(n, (y / 2) * 2 + x / 2);
+                *cell = self.child(child, (y % 2) * 2 + x % 2);
+            }
+        }
+        g
+    }
+
+    fn centre(&mut self, n: NodeId) -> Result<NodeId, TooBig> {
+        let g = self.grandchildren(n);
+        self.join([g[1][1], g[1][2], g[2][1], g[2][2]])
+    }
+
+    /// One generation of the centre 2x2 of a 4x4 node.
+    fn base(&mut self, n: NodeId) -> Result<NodeId, TooBig> {
+        let mut cells = [[CellState::Empty; 4]; 4];
+        let g = self.grandchildren(n);
+        for y in 0..4 {
+            for x in 0..4 {
+                cells[y][x] = self.nodes[g[y][x] as usize].state;
+            }
+        }
+        let mut children = [0; 4];
+        for (i, child) in children.iter_mut().enumerate() {
+            let x = 1 + (i & 1);
+            let y = 1 + (i >> 1);
+            let alive = self
+                .neighbourhood
+                .offsets()
+                .iter()
+                .filter(|(dx, dy)| {
+                    cells[(y as i32 + dy) as usize][(x as i32 + dx) as usize] == CellState::Alive
+                })
+                .count() as u32;
+            *child = self.leaf(self.rule.next(cells[y][x], alive));
+        }
+        self.join(children)
+    }
+
+    /// The centre half of `n` advanced by `2^j` generations, `j` being at most the level of
+    /// `n` minus two.
+    fn advance(&mut self, n: NodeId, j: u8) -> Result<NodeId, TooBig> {
+        let level = self.level(n);
+        if level == 2 {
+            return self.base(n);
+        }
+        if let Some(result) = self.results.get(&(n, j)) {
+            return Ok(*result);
+        }
+
+        let full = j == level - 2;
+        let step = if full { level - 3 } else { j };
+
+        let g = self.grandchildren(n);
+        let mut r = [[0; 3]; 3];
+        for y in 0..3 {
+            for x in 0..3 {
+                let square = self.join([g[y][x], g[y][x + 1], g[y + 1][x], g[y + 1][x + 1]])?;
+                r[y][x] = if full {
+                    self.advance(square, step)?
+                } else {
+                    self.centre(square)?
+                };
+            }
+        }
+
+        let mut children = [0; 4];
+        for (i, child) in children.iter_mut().enumerate() {
+            let x = i & 1;
+            let y = i >> 1;
+            let square = self.join([r[y][x], r[y][x + 1], r[y + 1][x], r[y + 1][x + 1]])?;
+            *child = self.advance(square, step)?;
+        }
+        let result = self.join(children)?;
+        self.results.insert((n, j), result);
+        Ok(result)
+    }
+
+    /// Whether `grid` can be jumped. The quadtree covers an infinite plane, there is no
+    /// way to tell it about edges.
+    pub fn supports(grid: &Grid) -> bool {
+        grid.topology().is_infinite()
+    }
+
+    /// Advances `grid` by `2^exponent` generations at once, `grid` must be
+    /// [supported](HashLife::supports).
+    ///
+    /// Running out of nodes drops the caches and tries again, if even that is not enough the
+    /// grid is left as it was.
+    pub fn jump(&mut self, grid: &mut Grid, exponent: u8) -> Result<(), TooBig> {
+        debug_assert!(Self::supports(grid));
+        let neighbourhood = grid.topology().neighbourhood;
+        if self.rule != grid.rule() || self.neighbourhood != neighbourhood {
+            self.rule = grid.rule();
+            self.neighbourhood = neighbourhood;
+            self.reset();
+        }
+
+        let cells: Vec<(Position, CellState)> = grid.cells().collect();
+        let next = match self.advance_cells(&cells, exponent) {
+            Err(TooBig) => {
+                self.reset();
+                self.advance_cells(&cells, exponent)
+            }
+            next => next,
+        }?;
+
+        for (p, _) in cells.iter() {
+            if !next.contains_key(p) {
+                grid.set(*p, CellState::Empty);
+            }
+        }
+        for (p, state) in next {
+            grid.set(p, state);
+        }
+        grid.set_generation(grid.generation() + (1 << exponent));
+        Ok(())
+    }
+
+    /// The non-empty cells `2^exponent` generations after `cells`.
+    fn advance_cells(
+        &mut self,
+        cells: &[(Position, CellState)],
+        exponent: u8,
+    ) -> Result<HashMap<Position, CellState>, TooBig> {
+        let (mut min, mut max) = match cells.first() {
+            Some((first, _)) => (*first, *first),
+            None => return Ok(HashMap::new()),
+        };
+        for (p, _) in cells.iter() {
+            min = min.min(*p);
+            max = max.max(*p);
+        }
+        let size = (max - min).x.max((max - min).y) as i64 + 1;
+
+        // The result only covers the centre of the root, so the pattern has to fit in there
+        // along with how far it can spread. Wireworld never grows past its conductors,
+        // other rules spread at most one cell per generation.
+        let margin = if self.rule.grows() {
+            1i64 << exponent
+        } else {
+            0
+        };
+        let mut level = (exponent + 2).max(3);
+        while (1i64 << (level - 1)) < size + 2 * margin {
+            level += 1;
+        }
+        let quarter = 1i64 << (level - 2);
+        let origin = (
+            min.x as i64 - quarter - margin,
+            min.y as i64 - quarter - margin,
+        );
+
+        self.empty(level)?;
+        let root = self.build(level, origin, cells)?;
+        let result = self.advance(root, exponent)?;
+
+        let mut next = Vec::new();
+        self.collect(result, (origin.0 + quarter, origin.1 + quarter), &mut next);
+        Ok(next.into_iter().collect())
+    }
+}
+
+fn quadrant_corner(origin: Corner, half: i64, i: usize) -> Corner {
+    (
+        origin.0 + (i & 1) as i64 * half,
+        origin.1 + (i >> 1) as i64 * half,
+    )
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::topology::Topology;
+
+    /// Deterministic xorshift, so failures can be reproduced.
+    fn random(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    }
+
+    fn sorted(grid: &Grid) -> Vec<(Position, CellState)> {
+        let mut cells: Vec<_> = grid.cells().collect();
+        cells.sort_by_key(|(p, _)| (p.y, p.x));
+        cells
+    }
+
+    /// A random soup around the origin, in an infinite world.
+    fn soup(rule: Rule, neighbourhood: Neighbourhood, seed: &mut u64) -> Grid {
+        let mut grid = Grid::with_rule(rule);
+        grid.set_topology(Topology {
+            neighbourhood,
+            ..Topology::default()
+        });
+        for _ in 0..150 {
+            let x = (random(seed) % 24) as i32 - 12;
+            let y = (random(seed) % 24) as i32 - 12;
+            let state = CellState((random(seed) % rule.states() as u64) as u8);
+            grid.set(Position { x, y }, state);
+        }
+        grid
+    }
+
+    fn copy(grid: &Grid) -> Grid {
+        let mut copy = Grid::with_rule(grid.rule());
+        copy.set_topology(grid.topology());
+        for (p, state) in grid.cells() {
+            copy.set(p, state);
+        }
+        copy
+    }
+
+    #[test]
+    fn jump_matches_step_naive() {
+        let mut seed = 4242;
+        for rule in Rule::Presets {
+            for neighbourhood in Neighbourhood::All {
+                let mut hashlife = HashLife::new();
+                for exponent in 0..=5 {
+                    let mut jumped = soup(rule, neighbourhood, &mut seed);
+                    let mut naive = copy(&jumped);
+
+                    hashlife.jump(&mut jumped, exponent).unwrap();
+                    for _ in 0..1 << exponent {
+                        naive.step_naive();
+                    }
+                    assert_eq!(
+                        sorted(&jumped),
+                        sorted(&naive),
+                        "{} {} 2^{}",
+                        rule,
+                        neighbourhood,
+                        exponent
+                    );
+                    assert_eq!(jumped.generation(), 1 << exponent);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_jumps_match_step_naive() {
+        let mut seed = 77;
+        for rule in Rule::Presets {
+            let mut hashlife = HashLife::new();
+            let mut jumped = soup(rule, Neighbourhood::Moore, &mut seed);
+            let mut naive = copy(&jumped);
+
+            for (i, exponent) in [3, 3, 5, 0, 4].into_iter().enumerate() {
+                hashlife.jump(&mut jumped, exponent).unwrap();
+                for _ in 0..1 << exponent {
+                    naive.step_naive();
+                }
+                assert_eq!(sorted(&jumped), sorted(&naive), "{} jump {}", rule, i);
+            }
+            assert_eq!(jumped.generation(), 8 + 8 + 32 + 1 + 16);
+        }
+    }
+}
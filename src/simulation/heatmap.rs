@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::grid::{Grid, PositionMap, PositionSet};
+
+/// How many generations the heatmap looks back.
+pub const WINDOW: usize = 256;
+
+/// How often each cell was a head, or alive under other rules, over the last [`WINDOW`]
+/// generations.
+#[derive(Default)]
+pub struct Heatmap {
+    /// Only counts while enabled, disabling forgets the counts.
+    pub enabled: bool,
+    /// Heads of each generation counted, oldest first.
+    frames: VecDeque<Vec<Position>>,
+    counts: PositionMap<u32>,
+    /// Cells whose count changed since [`Heatmap::take_changes`] was last called.
+    changes: PositionSet,
+    /// The last generation counted.
+    generation: Option<u64>,
+}
+
+impl Heatmap {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.changes.extend(self.counts.drain().map(|(p, _)| p));
+        self.generation = None;
+    }
+
+    /// Counts the heads of the generation `grid` was just stepped to, `changed` being the cells
+    /// the step changed.
+    ///
+    /// A head either just turned into one or was one the generation before, so only those cells
+    /// are looked at. Generations that do not follow the last one counted, after stepping back
+    /// or jumping, start over.
+    pub fn record(&mut self, grid: &Grid, changed: &[Position]) {
+        if !self.enabled {
+            return;
+        }
+        let generation = grid.generation();
+        if self.generation.map_or(false, |g| g + 1 != generation) {
+            self.clear();
+        }
+        self.generation = Some(generation);
+
+        let heads: Vec<Position> = match self.frames.back() {
+            Some(previous) => changed
+                .iter()
+                .chain(previous.iter())
+                .copied()
+                .filter(|p| grid.get(*p) == CellState::Head)
+                .collect(),
+            // Starting over, the heads that have not changed are not known yet
+            None => grid
+                .cells()
+                .filter(|(_, state)| *state == CellState::Head)
+                .map(|(p, _)| p)
+                .collect(),
+        };
+
+        for p in heads.iter() {
+            *self.counts.entry(*p).or_insert(0) += 1;
+        }
+        self.changes.extend(heads.iter().copied());
+        self.frames.push_back(heads);
+        if self.frames.len() > WINDOW {
+            for p in self.frames.pop_front().unwrap() {
+                self.changes.insert(p);
+                let count = self.counts.get_mut(&p).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&p);
+                }
+            }
+        }
+    }
+
+    /// Cells whose heat changed since the last call, by counting or by clearing.
+    pub fn take_changes(&mut self) -> PositionSet {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// How hot the cell at `p` is, from 0 for a cell that was never a head to 1 for one that was
+    /// a head every third generation, as often as Wireworld allows.
+    pub fn heat(&self, p: Position) -> f32 {
+        let count = self.counts.get(&p).copied().unwrap_or(0);
+        (count as f32 * 3. / WINDOW as f32).min(1.).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::rule::Rule;
+
+    #[test]
+    fn reports_the_cells_whose_heat_changed() {
+        let mut grid = Grid::new();
+        for x in 0..8 {
+            grid.set(Position::from((x, 0)), CellState::Conductor);
+        }
+        grid.set(Position::from((0, 0)), CellState::Head);
+        grid.set(Position::from((7, 5)), CellState::Conductor);
+        let mut heatmap = Heatmap::default();
+        heatmap.toggle();
+
+        let mut heated = PositionSet::default();
+        for _ in 0..4 {
+            let seen = grid.changes().len();
+            grid.step();
+            let changed = grid.changes()[seen..].to_vec();
+            heatmap.record(&grid, &changed);
+            let changes = heatmap.take_changes();
+            assert!(changes.iter().all(|p| heatmap.heat(*p) > 0.));
+            heated.extend(changes);
+        }
+        let expected: PositionSet = (1..5).map(|x| Position::from((x, 0))).collect();
+        assert_eq!(heated, expected);
+        assert!(heatmap.take_changes().is_empty());
+
+        // Hiding it cools every heated cell down
+        heatmap.toggle();
+        assert_eq!(heatmap.take_changes(), expected);
+        assert!(expected.iter().all(|p| heatmap.heat(*p) == 0.));
+    }
+
+    #[test]
+    fn still_lifes_stay_hot() {
+        let mut grid = Grid::with_rule(Rule::Life);
+        let block: Vec<Position> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .into_iter()
+            .map(Position::from)
+            .collect();
+        for p in block.iter() {
+            grid.set(*p, CellState::Alive);
+        }
+        let mut heatmap = Heatmap::default();
+        heatmap.toggle();
+
+        for _ in 0..WINDOW * 2 + 10 {
+            let seen = grid.changes().len();
+            grid.step();
+            let changed = grid.changes()[seen..].to_vec();
+            heatmap.record(&grid, &changed);
+        }
+        for p in block.iter() {
+            assert_eq!(heatmap.counts.get(p), Some(&(WINDOW as u32)), "{:?}", p);
+            assert_eq!(heatmap.heat(*p), 1.);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::grid::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellDiff {
+    pub position: Position,
+    pub before: CellState,
+    pub after: CellState,
+}
+
+/// Undo and redo stacks of edits, each edit being the list of cells it changed.
+///
+/// Edits made between [`History::begin`] and [`History::end`] are undone together, anything
+/// set outside of them is an edit of its own.
+pub struct History {
+    undo: VecDeque<Vec<CellDiff>>,
+    redo: Vec<Vec<CellDiff>>,
+    pending: Option<Vec<CellDiff>>,
+    depth: usize,
+}
+
+impl History {
+    /// Keeps at most `depth` edits to undo.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            pending: None,
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Starts grouping edits, does nothing if already grouping.
+    pub fn begin(&mut self) {
+        if self.pending.is_none() {
+            self.pending = Some(Vec::new());
+        }
+    }
+
+    /// Finishes the current group of edits, making it one entry to undo.
+    pub fn end(&mut self) {
+        if let Some(diffs) = self.pending.take() {
+            self.push(diffs);
+        }
+    }
+
+    /// Sets a cell in `grid`, recording the change.
+    pub fn set(&mut self, grid: &mut Grid, position: Position, state: CellState) {
+        let before = grid.set(position, state);
+        if before == state {
+            return;
+        }
+        let diff = CellDiff {
+            position,
+            before,
+            after: state,
+        };
+        match &mut self.pending {
+            Some(diffs) => diffs.push(diff),
+            None => self.push(vec![diff]),
+        }
+    }
+
+    /// Sets all `cells` in `grid` as a single edit.
+    pub fn apply(
+        &mut self,
+        grid: &mut Grid,
+        cells: impl IntoIterator<Item = (Position, CellState)>,
+    ) {
+        let open = self.is_open();
+        self.begin();
+        for (position, state) in cells {
+            self.set(grid, position, state);
+        }
+        if !open {
+            self.end();
+        }
+    }
+
+    fn push(&mut self, diffs: Vec<CellDiff>) {
+        if diffs.is_empty() {
+            return;
+        }
+        self.undo.push_back(diffs);
+        self.redo.clear();
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Reverts the last edit, returns false if there was nothing to undo.
+    pub fn undo(&mut self, grid: &mut Grid) -> bool {
+        self.end();
+        match self.undo.pop_back() {
+            Some(diffs) => {
+                for diff in diffs.iter().rev() {
+                    grid.set(diff.position, diff.before);
+                }
+                self.redo.push(diffs);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapplies the last undone edit, returns false if there was nothing to redo.
+    pub fn redo(&mut self, grid: &mut Grid) -> bool {
+        self.end();
+        match self.redo.pop() {
+            Some(diffs) => {
+                for diff in diffs.iter() {
+                    grid.set(diff.position, diff.after);
+                }
+                self.undo.push_back(diffs);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(grid: &Grid) -> Vec<(Position, CellState)> {
+        let mut cells: Vec<_> = grid.cells().collect();
+        cells.sort_by_key(|(p, _)| (p.y, p.x));
+        cells
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_cells() {
+        let mut grid = Grid::new();
+        let mut history = History::new(16);
+        let wire = |x| (Position::from((x, 0)), CellState::Conductor);
+        history.apply(&mut grid, (0..4).map(wire));
+        let wired = cells(&grid);
+
+        // A stroke overwriting and clearing cells, undone as one
+        history.begin();
+        history.set(&mut grid, Position::from((1, 0)), CellState::Head);
+        history.set(&mut grid, Position::from((1, 0)), CellState::Tail);
+        history.set(&mut grid, Position::from((3, 0)), CellState::Empty);
+        history.set(&mut grid, Position::from((-5, 2)), CellState::Conductor);
+        history.end();
+        let edited = cells(&grid);
+
+        assert!(history.undo(&mut grid));
+        assert_eq!(cells(&grid), wired);
+        assert!(history.undo(&mut grid));
+        assert!(grid.is_empty());
+        assert!(!history.undo(&mut grid));
+
+        assert!(history.redo(&mut grid));
+        assert_eq!(cells(&grid), wired);
+        assert!(history.redo(&mut grid));
+        assert_eq!(cells(&grid), edited);
+        assert!(!history.redo(&mut grid));
+    }
+
+    #[test]
+    fn a_new_edit_drops_the_redo_branch() {
+        let mut grid = Grid::new();
+        let mut history = History::new(16);
+        history.set(&mut grid, Position::from((0, 0)), CellState::Conductor);
+        history.set(&mut grid, Position::from((1, 0)), CellState::Conductor);
+        assert!(history.undo(&mut grid));
+
+        history.set(&mut grid, Position::from((0, 1)), CellState::Head);
+        assert!(!history.redo(&mut grid));
+        assert_eq!(grid.get(Position::from((1, 0))), CellState::Empty);
+
+        // Setting a cell to what it already is is not an edit, and keeps the redo branch
+        assert!(history.undo(&mut grid));
+        history.set(&mut grid, Position::from((0, 0)), CellState::Conductor);
+        assert!(history.redo(&mut grid));
+        assert_eq!(grid.get(Position::from((0, 1))), CellState::Head);
+    }
+
+    #[test]
+    fn only_depth_edits_are_kept() {
+        let mut grid = Grid::new();
+        let mut history = History::new(2);
+        for x in 0..3 {
+            history.set(&mut grid, Position::from((x, 0)), CellState::Conductor);
+        }
+        assert!(history.undo(&mut grid));
+        assert!(history.undo(&mut grid));
+        assert!(!history.undo(&mut grid));
+        assert_eq!(grid.get(Position::from((0, 0))), CellState::Conductor);
+    }
+}
@@ -0,0 +1,14 @@
+pub mod breakpoints;
+pub mod fill;
+pub mod grid;
+pub mod hashlife;
+pub mod heatmap;
+pub mod history;
+pub mod pattern;
+pub mod period;
+pub mod prefab;
+pub mod probes;
+pub mod rewind;
+pub mod route;
+pub mod rule;
+pub mod topology;
@@ -0,0 +1,182 @@
+use crate::components::{
+    area::Area, cell_state::CellState, direction::Direction, position::Position,
+};
+
+use super::grid::Grid;
+
+/// A rectangular block of cells detached from the grid, for moving circuits around.
+/// Cell positions are relative to the south-west corner of the block.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pattern {
+    pub width: i32,
+    pub height: i32,
+    pub cells: Vec<(Position, CellState)>,
+}
+
+impl Pattern {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            cells: Vec::new(),
+        }
+    }
+
+    /// Copies the non-empty cells inside `area`, which excludes its upper bound.
+    pub fn from_grid(grid: &Grid, area: Area) -> Self {
+        let size = area.size();
+        let mut pattern = Pattern::new(size.x, size.y);
+        for y in area.a.y..area.b.y {
+            for x in area.a.x..area.b.x {
+                let p = Position { x, y };
+                let state = grid.get(p);
+                if state != CellState::Empty {
+                    pattern.cells.push((p - area.a, state));
+                }
+            }
+        }
+        pattern
+    }
+
+    /// Writes the non-empty cells of the pattern into `grid` with its south-west corner at `at`.
+    pub fn stamp(&self, grid: &mut Grid, at: Position) {
+        for (p, state) in self.placed(at) {
+            grid.set(p, state);
+        }
+    }
+
+    /// The non-empty cells of the pattern in grid coordinates, with its south-west corner at `at`.
+    pub fn placed(&self, at: Position) -> impl Iterator<Item = (Position, CellState)> + '_ {
+        self.cells.iter().map(move |(p, state)| (at + *p, *state))
+    }
+
+    /// The pattern turned by `quarter_turns` quarter turns clockwise,
+    /// negative values turn counter clockwise.
+    pub fn rotate(&self, quarter_turns: i32) -> Pattern {
+        let mut pattern = self.clone();
+        for _ in 0..quarter_turns.rem_euclid(4) {
+            pattern = Pattern {
+                width: pattern.height,
+                height: pattern.width,
+                cells: pattern
+                    .cells
+                    .iter()
+                    .map(|(p, state)| {
+                        (
+                            p.rotate_cw() + Position::from((0, pattern.width - 1)),
+                            *state,
+                        )
+                    })
+                    .collect(),
+            };
+        }
+        pattern
+    }
+
+    /// The pattern turned so that a circuit drawn pointing north points in `direction`.
+    pub fn facing(&self, direction: Direction) -> Pattern {
+        self.rotate(direction.quarter_turns())
+    }
+
+    /// The pattern mirrored left to right.
+    pub fn flip_horizontal(&self) -> Pattern {
+        Pattern {
+            width: self.width,
+            height: self.height,
+            cells: self
+                .cells
+                .iter()
+                .map(|(p, state)| (Position::from((self.width - 1 - p.x, p.y)), *state))
+                .collect(),
+        }
+    }
+
+    /// The pattern mirrored top to bottom.
+    pub fn flip_vertical(&self) -> Pattern {
+        Pattern {
+            width: self.width,
+            height: self.height,
+            cells: self
+                .cells
+                .iter()
+                .map(|(p, state)| (Position::from((p.x, self.height - 1 - p.y)), *state))
+                .collect(),
+        }
+    }
+
+    /// Where to stamp the pattern so its top left corner lands on `cursor`.
+    pub fn top_left_at(&self, cursor: Position) -> Position {
+        cursor - Position::from((0, self.height - 1))
+    }
+
+    /// The area the pattern covers when stamped at `at`.
+    pub fn area(&self, at: Position) -> Area {
+        Area::new(at, at + Position::from((self.width, self.height)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An L of three cells in a 3x2 block, so every turn and mirror gives a different pattern.
+    fn l_shape() -> Pattern {
+        Pattern {
+            width: 3,
+            height: 2,
+            cells: vec![
+                (Position::from((0, 0)), CellState::Conductor),
+                (Position::from((1, 0)), CellState::Head),
+                (Position::from((0, 1)), CellState::Tail),
+            ],
+        }
+    }
+
+    fn in_bounds(pattern: &Pattern) -> bool {
+        pattern
+            .cells
+            .iter()
+            .all(|(p, _)| p.x >= 0 && p.y >= 0 && p.x < pattern.width && p.y < pattern.height)
+    }
+
+    #[test]
+    fn four_turns_are_the_identity() {
+        let pattern = l_shape();
+        for turns in 1..4 {
+            let turned = pattern.rotate(turns);
+            assert!(in_bounds(&turned), "{:?}", turned);
+            assert_ne!(turned, pattern);
+        }
+        assert_eq!(pattern.rotate(4), pattern);
+        assert_eq!(pattern.rotate(1).rotate(1).rotate(1).rotate(1), pattern);
+        assert_eq!(pattern.rotate(1).rotate(-1), pattern);
+        assert_eq!(pattern.rotate(-1), pattern.rotate(3));
+    }
+
+    #[test]
+    fn a_turn_is_clockwise() {
+        let turned = l_shape().rotate(1);
+        assert_eq!((turned.width, turned.height), (2, 3));
+        // The tail at the top left ends up at the top right
+        assert!(turned
+            .cells
+            .contains(&(Position::from((1, 2)), CellState::Tail)));
+        assert!(turned
+            .cells
+            .contains(&(Position::from((0, 1)), CellState::Head)));
+    }
+
+    #[test]
+    fn flipping_twice_is_the_identity() {
+        let pattern = l_shape();
+        let flipped = pattern.flip_horizontal();
+        assert!(in_bounds(&flipped));
+        assert_ne!(flipped, pattern);
+        assert_eq!(flipped.flip_horizontal(), pattern);
+
+        let flipped = pattern.flip_vertical();
+        assert!(in_bounds(&flipped));
+        assert_ne!(flipped, pattern);
+        assert_eq!(flipped.flip_vertical(), pattern);
+    }
+}
@@ -0,0 +1,229 @@
+//! Finding how often a region of the world repeats itself.
+
+use std::collections::HashMap;
+
+use crate::components::{area::Area, cell_state::CellState, position::Position};
+
+use super::grid::{Grid, PositionMap};
+
+/// Most generations run looking for a repeat.
+pub const MAX_GENERATIONS: u64 = 1 << 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    /// Generations between two repeats of the region.
+    pub period: u64,
+    /// Generations before the region starts repeating.
+    pub pre_period: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// No repeat yet after this many generations.
+    Running(u64),
+    Found(Period),
+    /// The region did not repeat within [`MAX_GENERATIONS`].
+    NotFound,
+}
+
+/// Hash of a cell, the hash of a region being all of its hashes xored together so changing
+/// a cell only takes its old hash out and the new one in.
+fn cell_hash(p: Position, state: CellState) -> u64 {
+    if state == CellState::Empty {
+        return 0;
+    }
+    // splitmix64 finalizer
+    let mut h = (p.x as u32 as u64) << 32 | p.y as u32 as u64;
+    h = h.wrapping_add((state.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Runs a copy of the world forward until the cells of an area come back to a state they were
+/// in before, a few generations at a time.
+///
+/// The whole world runs, so signals coming into the area from outside of it count. Every
+/// generation is hashed, and a hash seen before is only taken as a repeat once the cells it
+/// stands for are compared.
+pub struct Search {
+    area: Area,
+    world: Grid,
+    generation: u64,
+    hash: u64,
+    /// Generations reached with each hash.
+    seen: HashMap<u64, Vec<u64>>,
+    /// The cells of the area each generation changed, with their state before. Keeps memory
+    /// down to how busy the area is rather than how large.
+    frames: Vec<Vec<(Position, CellState)>>,
+    progress: Progress,
+}
+
+impl Search {
+    /// Starts looking for the period of `area`, from the current generation of `grid`.
+    pub fn new(grid: &Grid, area: Area) -> Self {
+        let mut world = Grid::with_rule(grid.rule());
+        world.set_topology(grid.topology());
+        let mut hash = 0;
+        for (p, state) in grid.cells() {
+            world.set(p, state);
+            if area.contains(p) {
+                hash ^= cell_hash(p, state);
+            }
+        }
+
+        let mut search = Self {
+            area,
+            world,
+            generation: 0,
+            hash,
+            seen: HashMap::new(),
+            frames: Vec::new(),
+            progress: Progress::Running(0),
+        };
+        search.seen.insert(hash, vec![0]);
+        search
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Runs at most `generations` more generations, stopping early once done.
+    pub fn run(&mut self, generations: u64) -> Progress {
+        let mut previous = Vec::new();
+        for _ in 0..generations {
+            if !matches!(self.progress, Progress::Running(_)) {
+                break;
+            }
+            if self.generation == MAX_GENERATIONS {
+                self.progress = Progress::NotFound;
+                break;
+            }
+
+            previous.clear();
+            self.world.step_recording(&mut previous);
+            // Nothing draws this copy of the world, the changes it keeps would only pile up
+            self.world.take_changes();
+            let mut frame = Vec::new();
+            for (p, old) in previous.iter() {
+                if self.area.contains(*p) {
+                    self.hash ^= cell_hash(*p, *old) ^ cell_hash(*p, self.world.get(*p));
+                    frame.push((*p, *old));
+                }
+            }
+            self.frames.push(frame);
+            self.generation += 1;
+
+            let generation = self.generation;
+            let earlier = self.seen.get(&self.hash).cloned().unwrap_or_default();
+            self.progress = match earlier.into_iter().find(|g| self.repeats(*g)) {
+                Some(first) => Progress::Found(Period {
+                    period: generation - first,
+                    pre_period: first,
+                }),
+                None => {
+                    self.seen.entry(self.hash).or_default().push(generation);
+                    Progress::Running(generation)
+                }
+            };
+        }
+        self.progress
+    }
+
+    /// Whether the area is the same as it was in generation `earlier`. Only the cells that
+    /// changed since then can differ, each being compared with its state the first time it
+    /// changed.
+    fn repeats(&self, earlier: u64) -> bool {
+        let mut before: PositionMap<CellState> = PositionMap::default();
+        for frame in self.frames[earlier as usize..].iter() {
+            for (p, old) in frame {
+                before.entry(*p).or_insert(*old);
+            }
+        }
+        before
+            .into_iter()
+            .all(|(p, state)| self.world.get(p) == state)
+    }
+}
+
+/// Runs [`Search`] on `area` to the end, see there.
+pub fn find(grid: &Grid, area: Area) -> Option<Period> {
+    let mut search = Search::new(grid, area);
+    match search.run(MAX_GENERATIONS + 1) {
+        Progress::Found(found) => Some(found),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::prefab;
+
+    fn placed_clock(period: u32, phase: u32) -> (Grid, Area) {
+        let mut grid = Grid::new();
+        let pattern = prefab::clock(period, phase);
+        pattern.stamp(&mut grid, Position::from((-3, 2)));
+        let area = pattern.area(Position::from((-3, 2)));
+        (grid, Area::new(area.a, area.b - Position::from((1, 1))))
+    }
+
+    #[test]
+    fn clocks_repeat_every_period() {
+        for period in [3, 4, 5, 8, 13, 21] {
+            for phase in 0..period {
+                let (grid, area) = placed_clock(period, phase);
+                let found = find(&grid, area).unwrap();
+                assert_eq!(found.period, period as u64, "phase {}", phase);
+                if phase == 0 {
+                    // The output wire holds no signal yet, the clock only repeats once the
+                    // first one left it
+                    assert_eq!(found.pre_period, 4, "period {}", period);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn runs_a_few_generations_at_a_time() {
+        let (grid, area) = placed_clock(21, 0);
+        let mut search = Search::new(&grid, area);
+        assert_eq!(search.run(10), Progress::Running(10));
+        assert_eq!(search.run(10), Progress::Running(20));
+        let found = Progress::Found(Period {
+            period: 21,
+            pre_period: 4,
+        });
+        assert_eq!(search.run(100), found);
+        assert_eq!(search.run(100), found);
+    }
+
+    #[test]
+    fn changes_do_not_pile_up() {
+        let (grid, area) = placed_clock(21, 0);
+        let mut search = Search::new(&grid, area);
+        search.run(10);
+        assert!(search.world.changes().is_empty());
+    }
+
+    #[test]
+    fn equal_hashes_are_not_taken_for_repeats() {
+        let (grid, area) = placed_clock(8, 0);
+        let mut ahead = Search::new(&grid, area);
+        ahead.run(1);
+
+        // Pretend the next generation hashes the same as the first, which it does not repeat
+        let mut search = Search::new(&grid, area);
+        search.seen.insert(ahead.hash, vec![0]);
+        assert_eq!(search.run(1), Progress::Running(1));
+        assert_eq!(search.seen[&ahead.hash], vec![0, 1]);
+        assert_eq!(
+            search.run(100),
+            Progress::Found(Period {
+                period: 8,
+                pre_period: 4,
+            })
+        );
+    }
+}
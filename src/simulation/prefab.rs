@@ -0,0 +1,330 @@
+//! Ready made Wireworld parts.
+//!
+//! Parts are drawn with signals flowing east, inputs on the west side and the output on the
+//! east side. Gates expect the signals on their inputs to arrive in the same generation.
+
+use std::fmt::{self, Display};
+
+use crate::components::{cell_state::CellState, direction::Direction, position::Position};
+
+use super::pattern::Pattern;
+
+/// Shortest period of a clock, a loop of three cells.
+pub const MIN_PERIOD: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefab {
+    /// Lets signals through one way only.
+    Diode(Direction),
+    Or,
+    Xor,
+    /// Passes the west input A unless the south input B, the inhibitor, fires at the same time.
+    AndNot,
+    /// A loop sending a signal every `period` generations.
+    Clock(u32),
+    /// Each signal arriving in step with the loop turns it on or off. While on, the output
+    /// fires every [`Prefab::FlipFlopPeriod`] generations.
+    FlipFlop,
+}
+
+impl Prefab {
+    pub const FlipFlopPeriod: u32 = 21;
+
+    /// The built in parts, in the order the palette shows them.
+    pub const Builtin: [Prefab; 9] = [
+        Prefab::Diode(Direction::East),
+        Prefab::Diode(Direction::North),
+        Prefab::Diode(Direction::West),
+        Prefab::Diode(Direction::South),
+        Prefab::Or,
+        Prefab::Xor,
+        Prefab::AndNot,
+        Prefab::Clock(8),
+        Prefab::FlipFlop,
+    ];
+
+    pub fn pattern(&self) -> Pattern {
+        match self {
+            // Drawn pointing east, facing expects patterns pointing north
+            Prefab::Diode(direction) => {
+                art(&[".##.", "##.##", ".##."]).facing(direction.rotate(-2))
+            }
+            Prefab::Or => art(&["##...", "..#..", ".####", "..#..", "##..."]),
+            Prefab::Xor => art(&[
+                "##.....", "..#....", ".####..", ".#..###", ".####..", "..#....", "##.....",
+            ]),
+            Prefab::AndNot => art(&["###.##", "...#..", "..###.", "...#..", "...#.."]),
+            Prefab::Clock(period) => clock(*period, 0),
+            Prefab::FlipFlop => art(&[
+                "##########",
+                "#........#",
+                "##.......#",
+                "..#......###",
+                ".####....#",
+                ".#..######",
+                ".####.....",
+                "..#.......",
+                "##........",
+            ]),
+        }
+    }
+}
+
+impl Display for Prefab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Prefab::Diode(direction) => {
+                let name = match direction.quarter_turns() {
+                    0 => "north",
+                    1 => "east",
+                    2 => "south",
+                    _ => "west",
+                };
+                write!(f, "Diode {}", name)
+            }
+            Prefab::Or => write!(f, "OR"),
+            Prefab::Xor => write!(f, "XOR"),
+            Prefab::AndNot => write!(f, "AND-NOT"),
+            Prefab::Clock(period) => write!(f, "Clock, period {}", period),
+            Prefab::FlipFlop => write!(f, "Flip-flop"),
+        }
+    }
+}
+
+/// A pattern from rows of text, top row first, with `#` for conductor and anything else empty.
+fn art(rows: &[&str]) -> Pattern {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as i32;
+    let height = rows.len() as i32;
+    let mut pattern = Pattern::new(width, height);
+    for (i, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c == '#' {
+                let p = Position::from((x as i32, height - 1 - i as i32));
+                pattern.cells.push((p, CellState::Conductor));
+            }
+        }
+    }
+    pattern
+}
+
+/// Cells of a loop of `length` cells in the order signals go around it, and the index of the
+/// cell the output tap is attached to. Each cell touches only the one before and after it, so
+/// a signal takes exactly `length` generations to go around.
+fn clock_loop(length: u32) -> (Vec<Position>, usize) {
+    let p = |x: i32, y: i32| Position { x, y };
+    if length == 3 {
+        return (vec![p(0, 0), p(1, 0), p(0, 1)], 1);
+    }
+
+    // Loops are a row along the bottom and one along the top, joined at both ends by cells
+    // cut diagonally across the corners. Even loops are two rows high, odd ones three rows
+    // high with one more cell in the top row, their right end coming to a point.
+    let a = if length % 2 == 0 {
+        (length as i32 - 2) / 2
+    } else {
+        (length as i32 - 5) / 2
+    };
+    let mut cells = vec![p(0, 1)];
+    cells.extend((1..=a).map(|x| p(x, 0)));
+    let tap = if length % 2 == 0 {
+        cells.push(p(a + 1, 1));
+        let tap = cells.len() - 1;
+        cells.extend((1..=a).rev().map(|x| p(x, 2)));
+        tap
+    } else {
+        cells.push(p(a + 1, 1));
+        cells.push(p(a + 2, 2));
+        let tap = cells.len() - 1;
+        cells.extend((1..=a + 1).rev().map(|x| p(x, 3)));
+        cells.push(p(0, 2));
+        tap
+    };
+    (cells, tap)
+}
+
+/// A clock sending a signal east every `period` generations, at least [`MIN_PERIOD`].
+///
+/// The cell right of the loop fires `phase` generations after the clock is placed, `period`
+/// generations when `phase` is 0, and again every `period` generations after that.
+pub fn clock(period: u32, phase: u32) -> Pattern {
+    let period = period.max(MIN_PERIOD);
+    // No loop of five cells keeps its signal from cutting corners, two signals in a loop of
+    // ten do the same
+    let signals = if period == 5 { 2 } else { 1 };
+    let (cells, tap) = clock_loop(period * signals);
+    let length = cells.len();
+
+    // The output is made of the tap and two more cells, right of the tap cell of the loop
+    let output: Vec<Position> = (1..=3)
+        .map(|x| cells[tap] + Position::from((x, 0)))
+        .collect();
+
+    let mut pattern = Pattern::new(
+        output[2].x + 1,
+        cells.iter().map(|p| p.y).max().unwrap() + 1,
+    );
+    pattern
+        .cells
+        .extend(output.iter().map(|p| (*p, CellState::Conductor)));
+
+    // The tap fires a generation after the loop cell next to it
+    let arrival = (phase % period + period - 1) as usize;
+    let mut states = vec![CellState::Conductor; length];
+    for signal in 0..signals as usize {
+        let head = (tap + length * 2 - arrival - signal * period as usize) % length;
+        states[head] = CellState::Head;
+        states[(head + length - 1) % length] = CellState::Tail;
+    }
+    pattern.cells.extend(cells.into_iter().zip(states));
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::grid::Grid;
+
+    /// A cell on the edge of a part and the side a wire leaves it from.
+    type End = (Position, Direction);
+
+    /// Length of the wires leading into and out of a part.
+    const WIRE: i32 = 4;
+
+    fn end(x: i32, y: i32, side: Direction) -> End {
+        (Position::from((x, y)), side)
+    }
+
+    /// The `i`th cell of the wire at `end`.
+    fn along((p, side): End, i: i32) -> Position {
+        let step = Position::from(side);
+        Position::from((p.x + step.x * i, p.y + step.y * i))
+    }
+
+    /// `pattern` placed at the origin, with a wire leading out of each of `ends`.
+    fn wired(pattern: &Pattern, ends: &[End]) -> Grid {
+        let mut grid = Grid::new();
+        pattern.stamp(&mut grid, Position::from((0, 0)));
+        for end in ends {
+            for i in 1..=WIRE {
+                grid.set(along(*end, i), CellState::Conductor);
+            }
+        }
+        grid
+    }
+
+    /// Sends a signal into the part along the wire at `end`.
+    fn send(grid: &mut Grid, end: End) {
+        grid.set(along(end, 1), CellState::Head);
+        grid.set(along(end, 2), CellState::Tail);
+    }
+
+    /// Generations, out of the next `generations`, in which a signal reaches the far end of the
+    /// wire at `end`.
+    fn arrivals(grid: &mut Grid, end: End, generations: u64) -> Vec<u64> {
+        (0..generations)
+            .filter_map(|_| {
+                grid.step();
+                (grid.get(along(end, WIRE)) == CellState::Head).then(|| grid.generation())
+            })
+            .collect()
+    }
+
+    /// Whether a gate with inputs `a` and `b` fires on `output`, for each of the four ways the
+    /// inputs can fire together.
+    fn truth_table(prefab: Prefab, a: End, b: End, output: End) -> [bool; 4] {
+        let pattern = prefab.pattern();
+        [(false, false), (false, true), (true, false), (true, true)].map(|(fire_a, fire_b)| {
+            let mut grid = wired(&pattern, &[a, b, output]);
+            if fire_a {
+                send(&mut grid, a);
+            }
+            if fire_b {
+                send(&mut grid, b);
+            }
+            !arrivals(&mut grid, output, 30).is_empty()
+        })
+    }
+
+    #[test]
+    fn diodes_pass_one_way_only() {
+        for direction in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let diode = Prefab::Diode(direction);
+            let pattern = diode.pattern();
+            let step = Position::from(direction);
+            let distance = |p: &Position| p.x * step.x + p.y * step.y;
+            let cells = pattern.cells.iter().map(|(p, _)| *p);
+            let input = (cells.clone().min_by_key(distance).unwrap(), -direction);
+            let output = (cells.max_by_key(distance).unwrap(), direction);
+
+            let mut grid = wired(&pattern, &[input, output]);
+            send(&mut grid, input);
+            assert_eq!(arrivals(&mut grid, output, 20).len(), 1, "{}", diode);
+
+            let mut grid = wired(&pattern, &[input, output]);
+            send(&mut grid, output);
+            assert!(arrivals(&mut grid, input, 20).is_empty(), "{}", diode);
+        }
+    }
+
+    #[test]
+    fn or_gate() {
+        let table = truth_table(
+            Prefab::Or,
+            end(0, 4, Direction::West),
+            end(0, 0, Direction::West),
+            end(4, 2, Direction::East),
+        );
+        assert_eq!(table, [false, true, true, true]);
+    }
+
+    #[test]
+    fn xor_gate() {
+        let table = truth_table(
+            Prefab::Xor,
+            end(0, 6, Direction::West),
+            end(0, 0, Direction::West),
+            end(6, 3, Direction::East),
+        );
+        assert_eq!(table, [false, true, true, false]);
+    }
+
+    #[test]
+    fn and_not_gate() {
+        // A comes in from the west, the inhibitor B from the south
+        let table = truth_table(
+            Prefab::AndNot,
+            end(0, 4, Direction::West),
+            end(3, 0, Direction::South),
+            end(5, 4, Direction::East),
+        );
+        assert_eq!(table, [false, false, true, false]);
+    }
+
+    #[test]
+    fn flip_flop_toggles() {
+        let input = end(0, 0, Direction::West);
+        let output = end(11, 5, Direction::East);
+        let period = Prefab::FlipFlopPeriod as u64;
+        let mut grid = wired(&Prefab::FlipFlop.pattern(), &[input, output]);
+        assert!(arrivals(&mut grid, output, 50).is_empty());
+
+        // On, firing once a period
+        send(&mut grid, input);
+        let fired = arrivals(&mut grid, output, period * 4);
+        assert_eq!(fired.len(), 4, "{:?}", fired);
+        assert!(
+            fired.windows(2).all(|w| w[1] - w[0] == period),
+            "{:?}",
+            fired
+        );
+
+        // Off again, the second signal meeting the one going around
+        send(&mut grid, input);
+        assert!(arrivals(&mut grid, output, period * 4).is_empty());
+    }
+}
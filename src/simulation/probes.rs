@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::grid::Grid;
+
+/// Most generations kept for each probe.
+pub const MAX_SAMPLES: usize = 1 << 14;
+
+/// A cell whose state is recorded every generation.
+pub struct Probe {
+    pub position: Position,
+    pub name: String,
+    /// Generation and the state of the cell in it, oldest first.
+    pub samples: VecDeque<(u64, CellState)>,
+}
+
+/// Cells pinned to watch their state over time.
+#[derive(Default)]
+pub struct Probes {
+    pub probes: Vec<Probe>,
+    named: usize,
+}
+
+impl Probes {
+    pub fn is_empty(&self) -> bool {
+        self.probes.is_empty()
+    }
+
+    /// Adds a probe on `position`, or removes the one already there. Returns whether a probe
+    /// was added.
+    pub fn toggle(&mut self, position: Position) -> bool {
+        if let Some(i) = self.probes.iter().position(|p| p.position == position) {
+            self.probes.remove(i);
+            return false;
+        }
+        self.probes.push(Probe {
+            position,
+            name: format!("p{}", self.named),
+            samples: VecDeque::new(),
+        });
+        self.named += 1;
+        true
+    }
+
+    /// Samples every probe at the current generation of `grid`.
+    ///
+    /// Samples of this generation or later are replaced, so recording again after an edit or
+    /// after stepping back keeps the samples in order.
+    pub fn record(&mut self, grid: &Grid) {
+        let generation = grid.generation();
+        for probe in self.probes.iter_mut() {
+            while probe
+                .samples
+                .back()
+                .map_or(false, |(g, _)| *g >= generation)
+            {
+                probe.samples.pop_back();
+            }
+            probe
+                .samples
+                .push_back((generation, grid.get(probe.position)));
+            if probe.samples.len() > MAX_SAMPLES {
+                probe.samples.pop_front();
+            }
+        }
+    }
+
+    /// Whether the last sample of every probe is the current state of its cell.
+    pub fn is_current(&self, grid: &Grid) -> bool {
+        self.probes.iter().all(|probe| {
+            probe.samples.back() == Some(&(grid.generation(), grid.get(probe.position)))
+        })
+    }
+
+    /// Generations covered by the samples, `None` without any.
+    pub fn span(&self) -> Option<(u64, u64)> {
+        let first = self
+            .probes
+            .iter()
+            .filter_map(|p| p.samples.front())
+            .map(|s| s.0)
+            .min()?;
+        let last = self
+            .probes
+            .iter()
+            .filter_map(|p| p.samples.back())
+            .map(|s| s.0)
+            .max()?;
+        Some((first, last))
+    }
+}
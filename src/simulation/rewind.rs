@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::grid::Grid;
+
+/// One generation worth of history, the cells that changed and what they were before.
+struct Frame {
+    generation: u64,
+    previous: Vec<(Position, CellState)>,
+}
+
+/// Recent generations of the grid, kept to step the simulation backwards.
+///
+/// Each generation only stores the cells that changed since the one before it, so a circuit
+/// with a few signals running through a lot of wire stays cheap to record.
+/// Editing the grid forgets everything recorded, as the old generations no longer lead to it.
+pub struct Rewind {
+    frames: VecDeque<Frame>,
+    cells: usize,
+    max_frames: usize,
+    max_cells: usize,
+    edits: u64,
+}
+
+impl Rewind {
+    /// Keeps at most `max_frames` generations, and fewer when they change more than
+    /// `max_cells` cells between them.
+    pub fn new(max_frames: usize, max_cells: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            cells: 0,
+            max_frames,
+            max_cells,
+            edits: 0,
+        }
+    }
+
+    /// How many generations can be stepped back.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Advances `grid` by one generation, remembering how to undo it.
+    pub fn step(&mut self, grid: &mut Grid) {
+        self.forget_if_edited(grid);
+
+        let generation = grid.generation();
+        let mut previous = Vec::new();
+        grid.step_recording(&mut previous);
+
+        self.cells += previous.len();
+        self.frames.push_back(Frame {
+            generation,
+            previous,
+        });
+        while self.frames.len() > self.max_frames || self.cells > self.max_cells {
+            match self.frames.pop_front() {
+                Some(frame) => self.cells -= frame.previous.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Restores `grid` to the generation before the current one, returns false if it was not
+    /// recorded.
+    pub fn step_back(&mut self, grid: &mut Grid) -> bool {
+        self.forget_if_edited(grid);
+
+        match self.frames.pop_back() {
+            Some(frame) => {
+                self.cells -= frame.previous.len();
+                for (p, state) in frame.previous.iter().rev() {
+                    grid.set(*p, *state);
+                }
+                grid.set_generation(frame.generation);
+                self.edits = grid.edits();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.cells = 0;
+    }
+
+    fn forget_if_edited(&mut self, grid: &Grid) {
+        if grid.edits() != self.edits {
+            self.clear();
+            self.edits = grid.edits();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(grid: &Grid) -> Vec<(Position, CellState)> {
+        let mut cells: Vec<_> = grid.cells().collect();
+        cells.sort_by_key(|(p, _)| (p.y, p.x));
+        cells
+    }
+
+    /// A loop of 10 cells with one signal running around it, changing 3 cells per generation.
+    fn loop_clock() -> Grid {
+        let mut grid = Grid::new();
+        for x in 0..5 {
+            grid.set(Position::from((x, 0)), CellState::Conductor);
+            grid.set(Position::from((x, 2)), CellState::Conductor);
+        }
+        grid.set(Position::from((-1, 1)), CellState::Conductor);
+        grid.set(Position::from((5, 1)), CellState::Conductor);
+        grid.set(Position::from((1, 2)), CellState::Tail);
+        grid.set(Position::from((2, 2)), CellState::Head);
+        grid
+    }
+
+    #[test]
+    fn keeps_the_last_generations() {
+        let mut grid = loop_clock();
+        let mut rewind = Rewind::new(4, usize::MAX);
+        let mut states = vec![cells(&grid)];
+        for _ in 0..10 {
+            rewind.step(&mut grid);
+            states.push(cells(&grid));
+            assert!(rewind.len() <= 4);
+        }
+        assert_eq!(rewind.len(), 4);
+
+        // The oldest frames were dropped, the newest come back in order
+        for generation in (6..10).rev() {
+            assert!(rewind.step_back(&mut grid));
+            assert_eq!(grid.generation(), generation);
+            assert_eq!(cells(&grid), states[generation as usize]);
+        }
+        assert!(!rewind.step_back(&mut grid));
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn keeps_recording_after_stepping_back() {
+        let mut grid = loop_clock();
+        let mut rewind = Rewind::new(4, usize::MAX);
+        let mut states = vec![cells(&grid)];
+        for _ in 0..6 {
+            rewind.step(&mut grid);
+            states.push(cells(&grid));
+        }
+        rewind.step_back(&mut grid);
+        rewind.step_back(&mut grid);
+        for _ in 0..3 {
+            rewind.step(&mut grid);
+        }
+        assert_eq!(rewind.len(), 4);
+        assert_eq!(grid.generation(), 7);
+
+        for generation in (3..7).rev() {
+            assert!(rewind.step_back(&mut grid));
+            assert_eq!(cells(&grid), states[generation as usize]);
+        }
+        assert!(!rewind.step_back(&mut grid));
+    }
+
+    #[test]
+    fn keeps_fewer_generations_that_change_many_cells() {
+        let mut grid = loop_clock();
+        let mut rewind = Rewind::new(100, 10);
+        for _ in 0..10 {
+            rewind.step(&mut grid);
+        }
+        assert_eq!(rewind.len(), 3);
+    }
+
+    #[test]
+    fn editing_forgets_the_recorded_generations() {
+        let mut grid = loop_clock();
+        let mut rewind = Rewind::new(4, usize::MAX);
+        rewind.step(&mut grid);
+        rewind.step(&mut grid);
+        grid.set(Position::from((10, 10)), CellState::Conductor);
+        assert!(!rewind.step_back(&mut grid));
+        assert_eq!(grid.generation(), 2);
+    }
+}
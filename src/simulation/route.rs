@@ -0,0 +1,183 @@
+//! Finding paths for new wires around existing circuitry.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::components::{cell_state::CellState, position::Position};
+
+use super::{
+    grid::{Grid, PositionMap},
+    topology::Neighbourhood,
+};
+
+/// How far outside the box spanned by its ends a route may wander.
+pub const MARGIN: i32 = 32;
+/// Most cells a search visits before giving up.
+pub const MAX_SEARCH: usize = 1 << 18;
+
+// Costs of a step, diagonal steps are about √2 times as long
+const STRAIGHT: u32 = 5;
+const DIAGONAL: u32 = 7;
+
+fn distance(a: Position, b: Position) -> u32 {
+    let d = (a - b).abs();
+    let (long, short) = (d.x.max(d.y) as u32, d.x.min(d.y) as u32);
+    (long - short) * STRAIGHT + short * DIAGONAL
+}
+
+fn touches(a: Position, b: Position) -> bool {
+    let d = (a - b).abs();
+    d.x <= 1 && d.y <= 1
+}
+
+/// The shortest wire from `from` to `to`, both ends included, stepping to any of the eight
+/// neighbouring cells.
+///
+/// A wire passing next to another one, even diagonally, would pick up its signals, so the
+/// route keeps an empty cell between itself and every existing cell. Only next to its ends,
+/// which are meant to join existing wires, may it touch them.
+///
+/// Returns `None` when there is no such route within [`MARGIN`] cells of the ends.
+pub fn route(grid: &Grid, from: Position, to: Position) -> Option<Vec<Position>> {
+    let topology = grid.topology();
+    let low = from.min(to) - Position::from((MARGIN, MARGIN));
+    let high = from.max(to) + Position::from((MARGIN, MARGIN));
+
+    let clear = |p: Position| {
+        if p == from || p == to {
+            return true;
+        }
+        if p.x < low.x || p.y < low.y || p.x > high.x || p.y > high.y {
+            return false;
+        }
+        if !topology.contains(p) || grid.get(p) != CellState::Empty {
+            return false;
+        }
+        touches(p, from)
+            || touches(p, to)
+            || Neighbourhood::Moore
+                .offsets()
+                .iter()
+                .all(|offset| grid.get(p + Position::from(*offset)) == CellState::Empty)
+    };
+
+    let mut came_from: PositionMap<Position> = PositionMap::default();
+    let mut cost: PositionMap<u32> = PositionMap::default();
+    let mut open = BinaryHeap::new();
+    cost.insert(from, 0);
+    open.push(Reverse((distance(from, to), 0, from.x, from.y)));
+
+    while let Some(Reverse((_, g, x, y))) = open.pop() {
+        let p = Position { x, y };
+        if p == to {
+            let mut path = vec![to];
+            let mut p = to;
+            while let Some(previous) = came_from.get(&p) {
+                p = *previous;
+                path.push(p);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if g > cost[&p] {
+            // Already reached more cheaply
+            continue;
+        }
+        if cost.len() > MAX_SEARCH {
+            return None;
+        }
+
+        for offset in Neighbourhood::Moore.offsets() {
+            let n = p + Position::from(*offset);
+            let step = if offset.0 != 0 && offset.1 != 0 {
+                DIAGONAL
+            } else {
+                STRAIGHT
+            };
+            let g = g + step;
+            if cost.get(&n).map_or(true, |best| g < *best) && clear(n) {
+                cost.insert(n, g);
+                came_from.insert(n, p);
+                open.push(Reverse((g + distance(n, to), g, n.x, n.y)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks `path` runs from `from` to `to` one step at a time, keeping away from every
+    /// existing cell except next to its ends.
+    fn assert_clear(grid: &Grid, path: &[Position], from: Position, to: Position) {
+        assert_eq!(path.first(), Some(&from));
+        assert_eq!(path.last(), Some(&to));
+        for pair in path.windows(2) {
+            assert!(
+                touches(pair[0], pair[1]) && pair[0] != pair[1],
+                "{:?}",
+                pair
+            );
+        }
+        for p in path {
+            if touches(*p, from) || touches(*p, to) {
+                continue;
+            }
+            for offset in Neighbourhood::Moore.offsets().iter().chain([&(0, 0)]) {
+                let n = *p + Position::from(*offset);
+                assert_eq!(grid.get(n), CellState::Empty, "{:?} touches {:?}", p, n);
+            }
+        }
+    }
+
+    #[test]
+    fn straight_without_obstacles() {
+        let grid = Grid::new();
+        let (from, to) = (Position::from((0, 0)), Position::from((6, 6)));
+        let path = route(&grid, from, to).unwrap();
+        assert_eq!(path.len(), 7);
+        assert_clear(&grid, &path, from, to);
+    }
+
+    #[test]
+    fn goes_around_existing_wires() {
+        let mut grid = Grid::new();
+        // A wall between the ends, and wires at both of them
+        for y in -5..=5 {
+            grid.set(Position::from((5, y)), CellState::Conductor);
+        }
+        for x in -3..0 {
+            grid.set(Position::from((x, 0)), CellState::Conductor);
+            grid.set(Position::from((x + 14, 0)), CellState::Head);
+        }
+        let (from, to) = (Position::from((0, 0)), Position::from((10, 0)));
+        let path = route(&grid, from, to).unwrap();
+        assert_clear(&grid, &path, from, to);
+        assert!(path.iter().any(|p| p.y.abs() > 6));
+    }
+
+    #[test]
+    fn enclosed_targets_are_unreachable() {
+        let mut grid = Grid::new();
+        let to = Position::from((20, 0));
+        for i in -3..=3 {
+            for p in [(i, -3), (i, 3), (-3, i), (3, i)] {
+                grid.set(to + Position::from(p), CellState::Conductor);
+            }
+        }
+        assert_eq!(route(&grid, Position::from((0, 0)), to), None);
+    }
+
+    #[test]
+    fn stays_within_the_margin() {
+        let mut grid = Grid::new();
+        for y in -MARGIN - 10..=MARGIN + 10 {
+            grid.set(Position::from((5, y)), CellState::Conductor);
+        }
+        assert_eq!(
+            route(&grid, Position::from((0, 0)), Position::from((10, 0))),
+            None
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! The transition rules a world can run, and their Golly style rulestrings.
+//!
+//! ```text
+//! WireWorld      Wireworld
+//! B3/S23         Conway's Life, any Life-like rule is written the same way
+//! B2/S/C3        Brian's Brain, a Generations rule with 3 states
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::components::cell_state::CellState;
+
+/// Neighbour counts go up to 8, so a rule fits its birth and survival conditions in 9 bits.
+const MAX_NEIGHBOURS: u32 = 8;
+
+/// How the cells of a world evolve. Every rule counts the neighbours in state 1, and the next
+/// state of a cell only depends on its own state and that count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rule {
+    /// Conductors become heads next to one or two heads, heads become tails and tails become
+    /// conductors again.
+    #[default]
+    Wireworld,
+    /// Life-like rules when `states` is 2, Generations rules above that.
+    /// Bit `n` of `birth` is set when an empty cell with `n` live neighbours comes alive, bit
+    /// `n` of `survive` when a live cell with `n` live neighbours stays alive. Live cells that
+    /// do not survive go through the dying states one generation each, then become empty.
+    Generations {
+        birth: u16,
+        survive: u16,
+        states: u8,
+    },
+}
+
+impl Rule {
+    pub const Life: Rule = Rule::Generations {
+        birth: 1 << 3,
+        survive: 1 << 2 | 1 << 3,
+        states: 2,
+    };
+    pub const BriansBrain: Rule = Rule::Generations {
+        birth: 1 << 2,
+        survive: 0,
+        states: 3,
+    };
+    pub const StarWars: Rule = Rule::Generations {
+        birth: 1 << 2,
+        survive: 1 << 3 | 1 << 4 | 1 << 5,
+        states: 4,
+    };
+
+    /// The rules offered when cycling through them in the editor.
+    pub const Presets: [Rule; 4] = [
+        Rule::Wireworld,
+        Rule::Life,
+        Rule::BriansBrain,
+        Rule::StarWars,
+    ];
+
+    /// Number of states including empty.
+    pub fn states(&self) -> u8 {
+        match self {
+            Rule::Wireworld => 4,
+            Rule::Generations { states, .. } => *states,
+        }
+    }
+
+    /// The state of a cell in the next generation, `alive` being how many of its neighbours are
+    /// in state 1.
+    pub fn next(&self, state: CellState, alive: u32) -> CellState {
+        match *self {
+            Rule::Wireworld => match state {
+                CellState::Conductor if alive == 1 || alive == 2 => CellState::Head,
+                CellState::Head => CellState::Tail,
+                CellState::Tail => CellState::Conductor,
+                state => state,
+            },
+            Rule::Generations {
+                birth,
+                survive,
+                states,
+            } => match state {
+                CellState::Empty if birth & (1 << alive) != 0 => CellState::Alive,
+                CellState::Empty => CellState::Empty,
+                CellState::Alive if survive & (1 << alive) != 0 => CellState::Alive,
+                CellState(dying) if dying + 1 < states => CellState(dying + 1),
+                _ => CellState::Empty,
+            },
+        }
+    }
+
+    /// Whether a cell in `state` may change even with no live neighbours. Only these cells and
+    /// the neighbours of live ones need to be looked at when stepping.
+    pub fn is_active(&self, state: CellState) -> bool {
+        match self {
+            Rule::Wireworld => state == CellState::Head || state == CellState::Tail,
+            Rule::Generations { .. } => state != CellState::Empty,
+        }
+    }
+
+    /// Whether the next state of a cell in `state` depends on its neighbours, only conductors
+    /// in Wireworld, empty and live cells in the other rules.
+    pub fn counts_neighbours(&self, state: CellState) -> bool {
+        match self {
+            Rule::Wireworld => state == CellState::Conductor,
+            Rule::Generations { .. } => state == CellState::Empty || state == CellState::Alive,
+        }
+    }
+
+    /// Whether patterns can spread into empty space, Wireworld signals never leave their wires.
+    pub fn grows(&self) -> bool {
+        !matches!(self, Rule::Wireworld)
+    }
+
+    /// The state drawn when placing cells.
+    pub fn placed(&self) -> CellState {
+        match self {
+            Rule::Wireworld => CellState::Conductor,
+            Rule::Generations { .. } => CellState::Alive,
+        }
+    }
+
+    /// What energizing turns `state` into, a conductor gets a head and a dying cell comes back
+    /// alive.
+    pub fn energized(&self, state: CellState) -> CellState {
+        match self {
+            Rule::Wireworld if state == CellState::Conductor => CellState::Head,
+            Rule::Generations { .. } if state != CellState::Empty => CellState::Alive,
+            _ => state,
+        }
+    }
+
+    /// What draining turns `state` into, signals become plain conductor and cells of the other
+    /// rules are removed.
+    pub fn drained(&self, state: CellState) -> CellState {
+        match self {
+            Rule::Wireworld if state == CellState::Head || state == CellState::Tail => {
+                CellState::Conductor
+            }
+            Rule::Wireworld => state,
+            Rule::Generations { .. } => CellState::Empty,
+        }
+    }
+
+    /// Human readable name, for the presets this is the name the rule is known by.
+    pub fn name(&self) -> String {
+        match *self {
+            Rule::Wireworld => "Wireworld".to_string(),
+            Rule::Life => "Life".to_string(),
+            Rule::BriansBrain => "Brian's Brain".to_string(),
+            Rule::StarWars => "Star Wars".to_string(),
+            rule => rule.to_string(),
+        }
+    }
+
+    /// Next preset after this rule, wrapping around.
+    pub fn next_preset(&self) -> Rule {
+        let i = Rule::Presets.iter().position(|rule| rule == self);
+        Rule::Presets[i.map_or(0, |i| (i + 1) % Rule::Presets.len())]
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Rule::Wireworld => write!(f, "WireWorld"),
+            Rule::Generations {
+                birth,
+                survive,
+                states,
+            } => {
+                write!(f, "B{}/S{}", digits(birth), digits(survive))?;
+                if states > 2 {
+                    write!(f, "/C{}", states)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn digits(bits: u16) -> String {
+    (0..=MAX_NEIGHBOURS)
+        .filter(|n| bits & (1 << n) != 0)
+        .map(|n| char::from_digit(n, 10).unwrap())
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RuleError(pub String);
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for RuleError {}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// Reads `WireWorld`, `Life`, `BriansBrain`, `B3/S23`, `B2/S/C3` and the older `S/B/C`
+    /// notation Golly also accepts, like `23/3` or `345/2/4`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || RuleError(format!("'{}' is not a supported rule", s));
+        match s.to_ascii_lowercase().as_str() {
+            "wireworld" => return Ok(Rule::Wireworld),
+            "life" => return Ok(Rule::Life),
+            "briansbrain" => return Ok(Rule::BriansBrain),
+            "starwars" => return Ok(Rule::StarWars),
+            _ => {}
+        }
+
+        let parts: Vec<&str> = s.split('/').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(invalid());
+        }
+        let prefixed = parts[0].starts_with(['B', 'b']);
+        let (birth, survive) = if prefixed {
+            let survive = parts[1].strip_prefix(['S', 's']).ok_or_else(invalid)?;
+            (&parts[0][1..], survive)
+        } else {
+            (parts[1], parts[0])
+        };
+        let states = match parts.get(2) {
+            None => 2,
+            Some(c) => c
+                .trim_start_matches(['C', 'c', 'G', 'g'])
+                .parse()
+                .map_err(|_| invalid())?,
+        };
+
+        let birth = neighbour_counts(birth).ok_or_else(invalid)?;
+        let survive = neighbour_counts(survive).ok_or_else(invalid)?;
+        if states < 2 {
+            return Err(RuleError(format!("'{}' needs at least 2 states", s)));
+        }
+        if birth & 1 != 0 {
+            return Err(RuleError(format!(
+                "'{}' has births without neighbours, which fill the infinite plane",
+                s
+            )));
+        }
+        Ok(Rule::Generations {
+            birth,
+            survive,
+            states,
+        })
+    }
+}
+
+fn neighbour_counts(digits: &str) -> Option<u16> {
+    digits.chars().try_fold(0, |bits, c| match c.to_digit(10) {
+        Some(n) if n <= MAX_NEIGHBOURS => Some(bits | 1 << n),
+        _ => None,
+    })
+}
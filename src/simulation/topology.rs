@@ -0,0 +1,197 @@
+//! The shape of a world: which cells count as neighbours and what happens at its edges.
+
+use std::fmt::{self, Display};
+
+use crate::components::position::Position;
+
+const MOORE: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+const VON_NEUMANN: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+// Rows are shifted half a cell to the right of the row below, so the cells above are the one
+// straight up and the one up and to the left, and the other way around below.
+const HEXAGONAL: [(i32, i32); 6] = [(0, 1), (-1, 1), (1, 0), (0, -1), (1, -1), (-1, 0)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Neighbourhood {
+    /// The eight surrounding cells.
+    #[default]
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
+    /// Six cells, as on a hexagonal grid stored in axial coordinates.
+    Hexagonal,
+}
+
+impl Neighbourhood {
+    pub const All: [Neighbourhood; 3] = [
+        Neighbourhood::Moore,
+        Neighbourhood::VonNeumann,
+        Neighbourhood::Hexagonal,
+    ];
+
+    /// Offsets of the neighbours of a cell, none further than one cell away on either axis.
+    pub fn offsets(&self) -> &'static [(i32, i32)] {
+        match self {
+            Neighbourhood::Moore => &MOORE,
+            Neighbourhood::VonNeumann => &VON_NEUMANN,
+            Neighbourhood::Hexagonal => &HEXAGONAL,
+        }
+    }
+
+    pub fn next(&self) -> Neighbourhood {
+        let i = Self::All.iter().position(|n| n == self).unwrap();
+        Self::All[(i + 1) % Self::All.len()]
+    }
+}
+
+impl Display for Neighbourhood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Neighbourhood::Moore => write!(f, "moore"),
+            Neighbourhood::VonNeumann => write!(f, "von-neumann"),
+            Neighbourhood::Hexagonal => write!(f, "hexagonal"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Edges {
+    /// No edges, the plane goes on forever.
+    #[default]
+    Infinite,
+    /// Nothing exists past the edges.
+    Bounded,
+    /// Leaving through one edge enters through the opposite one.
+    Torus,
+    /// Like a torus, but crossing the top or bottom edge mirrors left and right.
+    KleinBottle,
+}
+
+impl Edges {
+    pub const All: [Edges; 4] = [
+        Edges::Infinite,
+        Edges::Bounded,
+        Edges::Torus,
+        Edges::KleinBottle,
+    ];
+
+    pub fn next(&self) -> Edges {
+        let i = Self::All.iter().position(|e| e == self).unwrap();
+        Self::All[(i + 1) % Self::All.len()]
+    }
+}
+
+impl Display for Edges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edges::Infinite => write!(f, "infinite"),
+            Edges::Bounded => write!(f, "bounded"),
+            Edges::Torus => write!(f, "torus"),
+            Edges::KleinBottle => write!(f, "klein-bottle"),
+        }
+    }
+}
+
+/// Neighbourhood and edges of a world. Worlds with edges cover `width` by `height` cells from
+/// `origin`, infinite ones ignore those.
+///
+/// The hexagonal neighbourhood is not symmetric left to right, so a hexagonal Klein bottle
+/// would not be one. Hexagonal worlds with Klein bottle edges wrap like a torus instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Topology {
+    pub neighbourhood: Neighbourhood,
+    pub edges: Edges,
+    pub origin: Position,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Topology {
+    /// Size of a world when edges are first turned on.
+    pub const DefaultSize: i32 = 256;
+
+    pub fn is_infinite(&self) -> bool {
+        self.edges == Edges::Infinite
+    }
+
+    /// Whether every cell of the world, and the cells just outside of it its neighbours wrap
+    /// from, has a position.
+    pub fn fits(&self) -> bool {
+        let fits = |origin: i32, size: i32| {
+            origin.checked_sub(1).is_some()
+                && origin
+                    .checked_add(size)
+                    .and_then(|end| end.checked_add(1))
+                    .is_some()
+        };
+        self.is_infinite() || (fits(self.origin.x, self.width) && fits(self.origin.y, self.height))
+    }
+
+    /// Whether `p` is part of the world.
+    pub fn contains(&self, p: Position) -> bool {
+        self.is_infinite()
+            || (p.x >= self.origin.x
+                && p.y >= self.origin.y
+                && p.x < self.origin.x + self.width
+                && p.y < self.origin.y + self.height)
+    }
+
+    /// Where `p` ends up once the edges are applied, `None` if it falls off a bounded world.
+    /// Only meant for positions at most one cell outside of the world.
+    pub fn wrap(&self, p: Position) -> Option<Position> {
+        let wraps = match self.edges {
+            Edges::Infinite => return Some(p),
+            Edges::Bounded => return self.contains(p).then_some(p),
+            Edges::Torus => false,
+            Edges::KleinBottle => self.neighbourhood != Neighbourhood::Hexagonal,
+        };
+
+        let mut x = (p.x - self.origin.x).rem_euclid(self.width);
+        let y = p.y - self.origin.y;
+        let wrapped_y = y.rem_euclid(self.height);
+        if wraps && wrapped_y != y {
+            x = self.width - 1 - x;
+        }
+        Some(self.origin + Position::from((x, wrapped_y)))
+    }
+
+    /// Neighbours of the cell at `p`, after applying the edges.
+    pub fn neighbours(&self, p: Position) -> impl Iterator<Item = Position> + '_ {
+        self.neighbourhood
+            .offsets()
+            .iter()
+            .filter_map(move |offset| self.wrap(p + Position::from(*offset)))
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::default(),
+            edges: Edges::default(),
+            origin: Position::from((-Self::DefaultSize / 2, -Self::DefaultSize / 2)),
+            width: Self::DefaultSize,
+            height: Self::DefaultSize,
+        }
+    }
+}
+
+impl Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.neighbourhood)?;
+        if !self.is_infinite() {
+            write!(f, ", {} {}x{}", self.edges, self.width, self.height)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{
+    components::{cell_state::CellState, colors::Colors, position::Position, selection::Selection},
+    resources::{controls::Controls, head_count::HeadCountInput, status::Status},
+    simulation::{
+        breakpoints::{Breakpoint, Breakpoints, Hit},
+        grid::Grid,
+        topology::Neighbourhood,
+    },
+    utils::cell_to_world,
+    CELL_SIZE,
+};
+
+use super::{camera::MousePosition, clock::type_number, setup::BreakpointMarkersMarker};
+
+/// Sets and removes breakpoints: on the selected area when there is a selection, on the cell
+/// under the cursor otherwise, or on the number of heads with shift, typed in starting from
+/// the heads there are now.
+pub fn breakpoint_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mouse_position: Res<MousePosition>,
+    selection: Res<Selection>,
+    grid: Res<Grid>,
+    mut breakpoints: ResMut<Breakpoints>,
+    mut head_count: ResMut<HeadCountInput>,
+    mut status: ResMut<Status>,
+) {
+    if head_count.active {
+        type_head_count(&keys, &mut head_count, &mut breakpoints, &mut status);
+        return;
+    }
+    if !keys.just_pressed(Controls::Breakpoint) {
+        return;
+    }
+
+    if Controls::ctrl(&keys) {
+        breakpoints.breakpoints.clear();
+        breakpoints.hit = None;
+        status.info("Removed all breakpoints");
+        return;
+    }
+
+    let breakpoint = if Controls::shift(&keys) {
+        // Removes the head count already set, or asks for one
+        let set = breakpoints
+            .breakpoints
+            .iter()
+            .find(|b| matches!(b, Breakpoint::HeadsAbove(_)))
+            .copied();
+        match set {
+            Some(set) => set,
+            None => {
+                head_count.active = true;
+                head_count.typed = grid.count(CellState::Head).to_string();
+                show_head_count(&head_count, &mut status);
+                return;
+            }
+        }
+    } else if selection.active {
+        Breakpoint::AreaChanges(selection.cells())
+    } else {
+        Breakpoint::BecomesHead(mouse_position.cell)
+    };
+    if breakpoints.toggle(breakpoint) {
+        status.info(format!("Pausing when {}", breakpoint));
+    } else {
+        status.info(format!("No longer pausing when {}", breakpoint));
+    }
+}
+
+fn type_head_count(
+    keys: &Input<KeyCode>,
+    head_count: &mut HeadCountInput,
+    breakpoints: &mut Breakpoints,
+    status: &mut Status,
+) {
+    if keys.just_pressed(Controls::Cancel) {
+        head_count.active = false;
+        status.info("No head count breakpoint set");
+    } else if keys.just_pressed(Controls::SetHeadCount) {
+        if let Ok(n) = head_count.typed.parse() {
+            head_count.active = false;
+            let breakpoint = Breakpoint::HeadsAbove(n);
+            breakpoints.toggle(breakpoint);
+            status.info(format!("Pausing when {}", breakpoint));
+        } else {
+            status.error("Type the number of heads to pause above");
+        }
+    } else if type_number(keys, Controls::HeadCountErase, &mut head_count.typed) {
+        show_head_count(head_count, status);
+    }
+}
+
+fn show_head_count(head_count: &HeadCountInput, status: &mut Status) {
+    status.info(format!(
+        "Pause when the heads go above [{}]. Type the count, Return sets it, Escape cancels",
+        head_count.typed
+    ));
+}
+
+/// Marks the cells and areas with breakpoints, and the cell that set off the last one.
+pub fn update_breakpoint_markers(
+    mut commands: Commands,
+    breakpoints: Res<Breakpoints>,
+    grid: Res<Grid>,
+    mut shown: Local<(Vec<Breakpoint>, Option<Hit>, Option<Neighbourhood>)>,
+    markers_query: Query<Entity, With<BreakpointMarkersMarker>>,
+) {
+    let neighbourhood = grid.topology().neighbourhood;
+    if shown.0 == breakpoints.breakpoints
+        && shown.1 == breakpoints.hit
+        && shown.2 == Some(neighbourhood)
+    {
+        return;
+    }
+
+    let markers = markers_query.single();
+    commands.entity(markers).despawn_descendants();
+    commands.entity(markers).with_children(|parent| {
+        for breakpoint in breakpoints.breakpoints.iter() {
+            match breakpoint {
+                Breakpoint::BecomesHead(p) => {
+                    parent.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Colors::Breakpoint,
+                            custom_size: Some(Vec2::splat(CELL_SIZE * 1.5)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(
+                            cell_to_world(*p, neighbourhood).extend(0.),
+                        ),
+                        ..default()
+                    });
+                }
+                Breakpoint::AreaChanges(area) => {
+                    let corner = cell_to_world(area.a, neighbourhood) - 0.5 * CELL_SIZE;
+                    parent.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Colors::BreakpointArea,
+                            anchor: Anchor::BottomLeft,
+                            custom_size: Some(
+                                Vec2::from(area.size() + Position::from((1, 1))) * CELL_SIZE,
+                            ),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(corner.extend(0.)),
+                        ..default()
+                    });
+                }
+                Breakpoint::HeadsAbove(_) => (),
+            }
+        }
+
+        if let Some(cell) = breakpoints.hit.and_then(|hit| hit.cell) {
+            parent.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Colors::BreakpointHit,
+                    custom_size: Some(Vec2::splat(CELL_SIZE * 3.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    cell_to_world(cell, neighbourhood).extend(1.),
+                ),
+                ..default()
+            });
+        }
+    });
+    *shown = (
+        breakpoints.breakpoints.clone(),
+        breakpoints.hit,
+        Some(neighbourhood),
+    );
+}
@@ -3,7 +3,8 @@ use bevy::{input::mouse::MouseWheel, prelude::*};
 use crate::{
     components::position::Position,
     resources::controls::{ControlMode, Controls},
-    CELL_SIZE,
+    simulation::grid::Grid,
+    utils::world_to_cell,
 };
 
 #[derive(Component, Default)]
@@ -48,6 +49,7 @@ pub fn camera_movement(
     mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
     mut windows: Res<Windows>,
     mut zoom: ResMut<CameraZoom>,
+    grid: Res<Grid>,
 ) {
     // Calculate camera zoom
     let mut zoom_delta = 0.;
@@ -73,7 +75,7 @@ pub fn camera_movement(
 
     mouse_position.world =
         window_to_world_position(mouse_position.screen, windows, &camera_transform);
-    mouse_position.cell = (mouse_position.world / CELL_SIZE).round().into();
+    mouse_position.cell = world_to_cell(mouse_position.world, grid.topology().neighbourhood);
 
     // Move camera with keyboard
     if !dragging {
@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{cell_state::CellState, colors::Colors, selection::Selection},
+    formats::rle,
+    resources::{
+        area_action_event::{AreaAction, AreaActionEvent},
+        clipboard::{Clipboard, PastePreview},
+        controls::Controls,
+        status::Status,
+    },
+    simulation::{grid::Grid, history::History, pattern::Pattern, rule::Rule},
+    utils::cell_to_world,
+    CELL_SIZE,
+};
+
+use super::{camera::MousePosition, setup::PastePreviewMarker};
+
+pub fn clipboard_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut clipboard: ResMut<Clipboard>,
+    mut paste: ResMut<PastePreview>,
+    mut selection: ResMut<Selection>,
+    mut status: ResMut<Status>,
+    grid: Res<Grid>,
+    mut area_action_events: EventWriter<AreaActionEvent>,
+) {
+    if keys.just_pressed(Controls::Cancel) {
+        paste.pattern = None;
+    }
+
+    if !Controls::ctrl(&keys) {
+        return;
+    }
+
+    let copy = keys.just_pressed(Controls::Copy);
+    let cut = keys.just_pressed(Controls::Cut);
+    if copy || cut {
+        if !selection.active {
+            status.error("Select an area to copy first");
+            return;
+        }
+        let pattern = Pattern::from_grid(&grid, selection.area);
+        copy_to_os_clipboard(&pattern, grid.rule());
+        status.info(format!(
+            "{} {}x{} area",
+            if cut { "Cut" } else { "Copied" },
+            pattern.width,
+            pattern.height
+        ));
+        clipboard.pattern = Some(pattern);
+
+        if cut {
+            area_action_events.send(AreaActionEvent {
+                area: selection.area,
+                action: AreaAction::Delete,
+            });
+            selection.active = false;
+        }
+    } else if keys.just_pressed(Controls::Paste) {
+        // Patterns copied from other programs win over our own clipboard
+        let pattern = paste_from_os_clipboard(grid.rule()).or_else(|| clipboard.pattern.clone());
+        match pattern {
+            Some(pattern) => {
+                selection.active = false;
+                paste.pattern = Some(pattern);
+            }
+            None => status.error("Nothing to paste"),
+        }
+    }
+}
+
+fn copy_to_os_clipboard(pattern: &Pattern, rule: Rule) {
+    let result =
+        arboard::Clipboard::new().and_then(|mut os| os.set_text(rle::write(pattern, rule)));
+    if let Err(e) = result {
+        warn!("Could not copy to the system clipboard: {}", e);
+    }
+}
+
+/// Reads an RLE pattern from the system clipboard, ignoring patterns of other rules.
+fn paste_from_os_clipboard(rule: Rule) -> Option<Pattern> {
+    let text = arboard::Clipboard::new()
+        .and_then(|mut os| os.get_text())
+        .ok()?;
+    match rle::parse(&text).ok()? {
+        (_, Some(other)) if other != rule => {
+            warn!("Not pasting a {} pattern into a {} world", other, rule);
+            None
+        }
+        (pattern, _) => Some(pattern),
+    }
+}
+
+/// Rotates or mirrors the paste preview, or the selected cells when not pasting.
+pub fn transform_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut paste: ResMut<PastePreview>,
+    mut selection: ResMut<Selection>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History>,
+) {
+    if Controls::ctrl(&keys) {
+        return;
+    }
+
+    let transform: fn(&Pattern) -> Pattern = if keys.just_pressed(Controls::Rotate) {
+        if Controls::shift(&keys) {
+            |pattern| pattern.rotate(-1)
+        } else {
+            |pattern| pattern.rotate(1)
+        }
+    } else if keys.just_pressed(Controls::FlipHorizontal) {
+        Pattern::flip_horizontal
+    } else if keys.just_pressed(Controls::FlipVertical) {
+        Pattern::flip_vertical
+    } else {
+        return;
+    };
+
+    if paste.pattern.is_some() {
+        paste.pattern = paste.pattern.as_ref().map(transform);
+    } else if selection.active && !selection.selecting {
+        let at = selection.area.a;
+        let pattern = Pattern::from_grid(&grid, selection.area);
+        let transformed = transform(&pattern);
+        let cleared = pattern.placed(at).map(|(p, _)| (p, CellState::Empty));
+        history.apply(&mut grid, cleared.chain(transformed.placed(at)));
+        selection.area = transformed.area(at);
+    }
+}
+
+/// Stamps the paste preview into the grid on click.
+pub fn place_paste(
+    mouse_position: Res<MousePosition>,
+    mouse_button: Res<Input<MouseButton>>,
+    mut paste: ResMut<PastePreview>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History>,
+) {
+    if paste.wait_for_release && !mouse_button.pressed(Controls::MousePrimary) {
+        paste.wait_for_release = false;
+    }
+
+    if mouse_button.just_pressed(Controls::MousePrimary) {
+        if let Some(pattern) = paste.pattern.take() {
+            let at = pattern.top_left_at(mouse_position.cell);
+            history.apply(&mut grid, pattern.placed(at));
+            paste.wait_for_release = true;
+        }
+    }
+}
+
+/// Moves the ghost of the paste preview with the cursor, and rebuilds it when the pattern
+/// changes.
+pub fn update_paste_preview(
+    mut commands: Commands,
+    paste: Res<PastePreview>,
+    grid: Res<Grid>,
+    mouse_position: Res<MousePosition>,
+    mut preview_query: Query<(Entity, &mut Transform), With<PastePreviewMarker>>,
+) {
+    let (entity, mut transform) = preview_query.single_mut();
+    let neighbourhood = grid.topology().neighbourhood;
+
+    if paste.is_changed() {
+        commands.entity(entity).despawn_descendants();
+        if let Some(pattern) = &paste.pattern {
+            commands.entity(entity).with_children(|parent| {
+                for (p, state) in pattern.cells.iter() {
+                    if *state == CellState::Empty {
+                        continue;
+                    }
+                    let color = Colors::ghost(grid.rule(), *state);
+                    parent.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(Vec2::splat(CELL_SIZE)),
+                            ..default()
+                        },
+                        // The layout is linear, so offsets within the pattern map like cells
+                        transform: Transform::from_translation(
+                            cell_to_world(*p, neighbourhood).extend(0.),
+                        ),
+                        ..default()
+                    });
+                }
+            });
+        }
+    }
+
+    if let Some(pattern) = &paste.pattern {
+        transform.translation =
+            cell_to_world(pattern.top_left_at(mouse_position.cell), neighbourhood).extend(17.);
+    }
+}
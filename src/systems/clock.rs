@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::selection::Selection,
+    resources::{
+        clipboard::PastePreview,
+        clock_tool::{ClockField, ClockTool},
+        controls::Controls,
+        status::Status,
+    },
+    simulation::{
+        grid::Grid,
+        prefab::{self, MIN_PERIOD},
+        rule::Rule,
+    },
+};
+
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Adds the digits pressed to `typed`, up to six of them, and takes the last one off with
+/// `erase`. Whether `typed` changed.
+pub fn type_number(keys: &Input<KeyCode>, erase: KeyCode, typed: &mut String) -> bool {
+    let mut changed = false;
+    if keys.just_pressed(erase) {
+        typed.pop();
+        changed = true;
+    }
+    for (digit, key) in DIGITS.iter().enumerate() {
+        if keys.just_pressed(*key) && typed.len() < 6 {
+            typed.push(char::from_digit(digit as u32, 10).unwrap());
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Places loop clocks: the period and phase are typed in while the clock follows the cursor
+/// in the paste preview, a click places it.
+pub fn clock_tool(
+    keys: Res<Input<KeyCode>>,
+    grid: Res<Grid>,
+    mut tool: ResMut<ClockTool>,
+    mut paste: ResMut<PastePreview>,
+    mut selection: ResMut<Selection>,
+    mut status: ResMut<Status>,
+) {
+    if Controls::ctrl(&keys) {
+        return;
+    }
+
+    if keys.just_pressed(Controls::ClockTool) {
+        if grid.rule() != Rule::Wireworld {
+            status.error("Clocks are Wireworld circuits");
+            return;
+        }
+        tool.active = !tool.active;
+        tool.editing = ClockField::Period;
+        tool.typed.clear();
+        if !tool.active {
+            paste.pattern = None;
+            return;
+        }
+        selection.active = false;
+    } else if !tool.active {
+        return;
+    } else if paste.pattern.is_none() {
+        // Placed or cancelled
+        tool.active = false;
+        return;
+    }
+
+    let mut changed = keys.just_pressed(Controls::ClockTool);
+    if keys.just_pressed(Controls::ClockNextField) {
+        tool.editing = match tool.editing {
+            ClockField::Period => ClockField::Phase,
+            ClockField::Phase => ClockField::Period,
+        };
+        tool.typed.clear();
+        changed = true;
+    }
+    changed |= type_number(&keys, Controls::ClockErase, &mut tool.typed);
+    if !changed {
+        return;
+    }
+
+    if let Ok(value) = tool.typed.parse::<u32>() {
+        match tool.editing {
+            ClockField::Period if value >= MIN_PERIOD => tool.period = value,
+            ClockField::Phase => tool.phase = value % tool.period,
+            _ => (),
+        }
+    }
+    paste.pattern = Some(prefab::clock(tool.period, tool.phase));
+
+    let typing = |field: ClockField, value: u32| {
+        if tool.editing == field {
+            format!("[{}]", tool.typed)
+        } else {
+            value.to_string()
+        }
+    };
+    let message = format!(
+        "Clock, period {} phase {}. Type the numbers, Tab switches between them, click places",
+        typing(ClockField::Period, tool.period),
+        typing(ClockField::Phase, tool.phase)
+    );
+    if tool.editing == ClockField::Period
+        && tool.typed.parse::<u32>().map_or(false, |p| p < MIN_PERIOD)
+    {
+        status.error(format!("The period is at least {}", MIN_PERIOD));
+    } else {
+        status.info(message);
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    components::selection::Selection,
+    formats::{
+        rle,
+        save::{self, LoadError, SaveFile, View},
+    },
+    resources::{
+        controls::Controls,
+        document::Document,
+        file_prompt::{FileAction, FileChosen, FilePrompt},
+        status::Status,
+    },
+    simulation::{grid::Grid, history::History, pattern::Pattern, rule::Rule},
+};
+
+use super::camera::{CameraZoom, MousePosition};
+
+/// Asks for the file to save, open, import or export with ctrl and the matching key.
+pub fn file_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    document: Res<Document>,
+    selection: Res<Selection>,
+    mut prompt: ResMut<FilePrompt>,
+    mut chosen: EventWriter<FileChosen>,
+    mut status: ResMut<Status>,
+) {
+    if !Controls::ctrl(&keys) {
+        return;
+    }
+
+    if keys.just_pressed(Controls::Save) {
+        match document.path.clone() {
+            Some(path) if !Controls::shift(&keys) => chosen.send(FileChosen {
+                action: FileAction::Save,
+                path,
+            }),
+            path => ask_for_file(FileAction::Save, path, &mut prompt, &mut chosen),
+        }
+    } else if keys.just_pressed(Controls::Open) {
+        ask_for_file(
+            FileAction::Open,
+            document.path.clone(),
+            &mut prompt,
+            &mut chosen,
+        );
+    } else if keys.just_pressed(Controls::ImportPattern) {
+        ask_for_file(FileAction::ImportPattern, None, &mut prompt, &mut chosen);
+    } else if keys.just_pressed(Controls::ExportPattern) {
+        if !selection.active {
+            status.error("Select an area to export first");
+            return;
+        }
+        ask_for_file(FileAction::ExportPattern, None, &mut prompt, &mut chosen);
+    }
+}
+
+/// Asks for the file `action` works on, starting from `suggested`. Picked in a native dialog
+/// when built with the `native-dialogs` feature, typed into the status line otherwise.
+/// [`FileChosen`] is sent once there is one.
+pub fn ask_for_file(
+    action: FileAction,
+    suggested: Option<PathBuf>,
+    prompt: &mut FilePrompt,
+    chosen: &mut EventWriter<FileChosen>,
+) {
+    if cfg!(feature = "native-dialogs") {
+        if let Some(path) = native_dialog(action, suggested) {
+            chosen.send(FileChosen { action, path });
+        }
+        return;
+    }
+
+    prompt.action = Some(action);
+    prompt.typed = match suggested {
+        Some(path) => path.display().to_string(),
+        None if action.writes() => action.file_name(),
+        None => String::new(),
+    };
+}
+
+#[cfg(feature = "native-dialogs")]
+fn native_dialog(action: FileAction, suggested: Option<PathBuf>) -> Option<PathBuf> {
+    let (name, extension) = action.filter();
+    let mut dialog = rfd::FileDialog::new()
+        .set_title(action.title())
+        .add_filter(name, &[extension]);
+    if let Some(directory) = suggested.as_deref().and_then(Path::parent) {
+        dialog = dialog.set_directory(directory);
+    }
+    if action.writes() {
+        dialog.set_file_name(&action.file_name()).save_file()
+    } else {
+        dialog.pick_file()
+    }
+}
+
+#[cfg(not(feature = "native-dialogs"))]
+fn native_dialog(_action: FileAction, _suggested: Option<PathBuf>) -> Option<PathBuf> {
+    None
+}
+
+/// Takes the keys typed while a path is asked for, before any shortcut sees them. Return
+/// chooses the path and Escape gives up.
+pub fn file_prompt(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut prompt: ResMut<FilePrompt>,
+    mut chosen: EventWriter<FileChosen>,
+    mut status: ResMut<Status>,
+) {
+    let typed: String = characters
+        .iter()
+        .map(|c| c.char)
+        .filter(|c| !c.is_control())
+        .collect();
+    let action = match prompt.action {
+        Some(action) => action,
+        None => return,
+    };
+
+    prompt.typed.push_str(&typed);
+    if keys.just_pressed(Controls::ErasePath) {
+        prompt.typed.pop();
+    }
+    if keys.just_pressed(Controls::Cancel) {
+        prompt.action = None;
+        status.info(format!("{}: cancelled", action.title()));
+    } else if keys.just_pressed(Controls::ChoosePath) && !prompt.typed.trim().is_empty() {
+        prompt.action = None;
+        chosen.send(FileChosen {
+            action,
+            path: PathBuf::from(prompt.typed.trim()),
+        });
+    } else {
+        status.info(format!(
+            "{}: {}_  (Return chooses, Escape cancels)",
+            action.title(),
+            prompt.typed
+        ));
+    }
+    keys.clear();
+}
+
+/// Saves, opens, imports and exports the files chosen for them.
+#[allow(clippy::too_many_arguments)]
+pub fn open_files(
+    mut chosen: EventReader<FileChosen>,
+    mouse_position: Res<MousePosition>,
+    mut selection: ResMut<Selection>,
+    mut document: ResMut<Document>,
+    mut status: ResMut<Status>,
+    mut grid: ResMut<Grid>,
+    mut zoom: ResMut<CameraZoom>,
+    mut history: ResMut<History>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    for FileChosen { action, path } in chosen.iter() {
+        let path = path.clone();
+        match action {
+            FileAction::Save => {
+                let camera_transform = camera_query.single();
+                let view = View {
+                    x: camera_transform.translation.x,
+                    y: camera_transform.translation.y,
+                    zoom: zoom.0,
+                };
+                match save_circuit(&path, &grid, view) {
+                    Ok(()) => {
+                        status.info(format!("Saved {}", path.display()));
+                        document.path = Some(path);
+                    }
+                    Err(e) => {
+                        error!("Failed to save {}: {}", path.display(), e);
+                        status.error(format!("Could not save {}: {}", path.display(), e));
+                    }
+                }
+            }
+            FileAction::Open => match load_circuit(&path) {
+                Ok(save) => {
+                    grid.clear();
+                    grid.set_rule(save.rule);
+                    grid.set_topology(save.topology);
+                    for (p, state) in save.cells {
+                        grid.set(p, state);
+                    }
+                    grid.set_generation(save.generation);
+                    history.clear();
+                    let mut camera_transform = camera_query.single_mut();
+                    camera_transform.translation.x = save.view.x;
+                    camera_transform.translation.y = save.view.y;
+                    zoom.0 = save.view.zoom;
+                    selection.selecting = false;
+                    selection.active = false;
+
+                    status.info(format!("Opened {}", path.display()));
+                    document.path = Some(path);
+                }
+                Err(e) => {
+                    error!("Failed to open {}: {}", path.display(), e);
+                    status.error(format!("Could not open {}: {}", path.display(), e));
+                }
+            },
+            FileAction::ImportPattern => match import_pattern(&path) {
+                Ok((_, Some(rule))) if rule != grid.rule() && !grid.is_empty() => {
+                    status.error(format!(
+                        "{} is a {} pattern, this world runs {}",
+                        path.display(),
+                        rule.name(),
+                        grid.rule().name()
+                    ));
+                }
+                Ok((pattern, rule)) => {
+                    // An empty world takes on the rule of the first pattern imported into it
+                    if let Some(rule) = rule.filter(|rule| *rule != grid.rule()) {
+                        grid.set_rule(rule);
+                        history.clear();
+                    }
+                    let at = pattern.top_left_at(mouse_position.cell);
+                    history.apply(&mut grid, pattern.placed(at));
+                    status.info(format!(
+                        "Imported {}x{} pattern from {}",
+                        pattern.width,
+                        pattern.height,
+                        path.display()
+                    ));
+                }
+                Err(e) => {
+                    error!("Failed to import {}: {}", path.display(), e);
+                    status.error(format!("Could not import {}: {}", path.display(), e));
+                }
+            },
+            FileAction::ExportPattern => {
+                let pattern = Pattern::from_grid(&grid, selection.area);
+                match fs::write(&path, rle::write(&pattern, grid.rule())) {
+                    Ok(()) => status.info(format!("Exported selection to {}", path.display())),
+                    Err(e) => {
+                        error!("Failed to export {}: {}", path.display(), e);
+                        status.error(format!("Could not export {}: {}", path.display(), e));
+                    }
+                }
+            }
+            // Written by the probe systems
+            FileAction::ExportProbes => (),
+        }
+    }
+}
+
+fn import_pattern(path: &Path) -> Result<(Pattern, Option<Rule>), Box<dyn std::error::Error>> {
+    Ok(rle::parse(&fs::read_to_string(path)?)?)
+}
+
+fn save_circuit(path: &Path, grid: &Grid, view: View) -> std::io::Result<()> {
+    let save = SaveFile {
+        rule: grid.rule(),
+        topology: grid.topology(),
+        generation: grid.generation(),
+        view,
+        cells: grid.cells().collect(),
+    };
+    save::write(BufWriter::new(File::create(path)?), &save)
+}
+
+fn load_circuit(path: &Path) -> Result<SaveFile, LoadError> {
+    save::read(BufReader::new(File::open(path)?))
+}
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+use crate::{
+    resources::{controls::Controls, status::Status},
+    simulation::{grid::Grid, history::History},
+};
+
+pub fn history_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut status: ResMut<Status>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History>,
+) {
+    if !Controls::ctrl(&keys) {
+        return;
+    }
+
+    let redo = keys.just_pressed(Controls::Redo)
+        || (keys.just_pressed(Controls::Undo) && Controls::shift(&keys));
+    if redo {
+        if !history.redo(&mut grid) {
+            status.info("Nothing to redo");
+        }
+    } else if keys.just_pressed(Controls::Undo) {
+        if !history.undo(&mut grid) {
+            status.info("Nothing to undo");
+        }
+    }
+}
@@ -7,21 +7,36 @@ use bevy_ecs_tilemap::{
 use crate::{
     components::{
         area::Area,
+        auto_route::AutoRoute,
         cell_state::{CellState, CurrentState, NextState},
         line_assist::LineAssist,
+        path_tool::PathTool,
+        position::Position,
         selection::Selection,
     },
     resources::{
         area_action_event::{AreaAction, AreaActionEvent},
         cell_action::CellAction,
+        cells_action_event::CellsActionEvent,
+        clipboard::PastePreview,
         controls::{ControlMode, Controls},
         cursor::Cursor,
+        status::Status,
     },
-    AppState, SIZE,
+    simulation::{
+        fill::{self, MAX_FILL},
+        grid::Grid,
+        history::History,
+        route::{self, MARGIN},
+    },
+    AppState,
 };
 
 use super::camera::MousePosition;
 
+/// Longest time between two clicks on the same cell for them to count as a double click.
+const DOUBLE_CLICK: f64 = 0.4;
+
 pub fn handle_input(
     mut commands: Commands,
     mouse_position: Res<MousePosition>,
@@ -32,9 +47,17 @@ pub fn handle_input(
     app_state: Res<AppState>,
     mouse_button: Res<Input<MouseButton>>,
     keys: Res<Input<KeyCode>>,
+    paste: Res<PastePreview>,
+    path: Res<PathTool>,
+    auto_route: Res<AutoRoute>,
+    grid: Res<Grid>,
+    mut status: ResMut<Status>,
     mut area_action_events: EventWriter<AreaActionEvent>,
+    mut cells_action_events: EventWriter<CellsActionEvent>,
 ) {
-    if !line_assist.drawing
+    if paste.active() || path.active || auto_route.active {
+        // Clicks place the pasted pattern, add corners to the path or pick the ends of a route
+    } else if !line_assist.drawing
         && keys.pressed(Controls::SelectArea)
         && mouse_button.just_pressed(MouseButton::Left)
     {
@@ -58,9 +81,9 @@ pub fn handle_input(
         && (keys.just_released(Controls::DrawLine) || mouse_button.just_released(MouseButton::Left))
     {
         line_assist.drawing = false;
-        if controls.mode != ControlMode::Select {
-            area_action_events.send(AreaActionEvent {
-                area: line_assist.area,
+        if controls.mode.actionable() {
+            cells_action_events.send(CellsActionEvent {
+                cells: line_assist.cells.clone(),
                 action: controls.mode.into(),
             });
         }
@@ -69,15 +92,18 @@ pub fn handle_input(
     if selection.selecting {
         selection.area = Area::new(selection.origin, mouse_position.cell).grow((1, 1).into());
     } else if line_assist.drawing {
-        let a = line_assist.origin;
-        let mut b = mouse_position.cell;
-        let diff = (a - b).abs();
-        if diff.x > diff.y {
-            b.y = a.y;
-        } else {
-            b.x = a.x;
+        let cells = line_assist
+            .mode
+            .line(line_assist.origin, mouse_position.cell);
+        // Only touch the line when it changes, the preview is rebuilt every time it does
+        if cells != line_assist.cells {
+            line_assist.cells = cells;
         }
-        line_assist.area = Area::new(a, b).grow((1, 1).into());
+    }
+
+    // Keys pressed with ctrl are shortcuts, handled by their own systems
+    if Controls::ctrl(&keys) {
+        return;
     }
 
     for key in keys.get_just_pressed() {
@@ -94,7 +120,7 @@ pub fn handle_input(
                 } else {
                     controls.mode = ControlMode::Drain;
                 }
-            },
+            }
             Controls::DeleteMode => {
                 if selection.active {
                     // Fire area action event
@@ -106,6 +132,35 @@ pub fn handle_input(
                     controls.mode = ControlMode::Delete;
                 }
             }
+            Controls::FillSelection if !controls.mode.actionable() => {
+                status.error("Pick a mode to fill with first");
+            }
+            Controls::FillSelection if selection.active => {
+                area_action_events.send(AreaActionEvent {
+                    area: selection.area,
+                    action: controls.mode.into(),
+                });
+            }
+            Controls::FillSelection => {
+                match fill::region(&grid, mouse_position.cell, controls.connectivity) {
+                    Some(cells) => cells_action_events.send(CellsActionEvent {
+                        cells,
+                        action: controls.mode.into(),
+                    }),
+                    None => status.error(format!(
+                        "Not filling, the region is larger than {} cells",
+                        MAX_FILL
+                    )),
+                }
+            }
+            Controls::ToggleConnectivity => {
+                controls.connectivity = controls.connectivity.next();
+                status.info(format!("Filling {} regions", controls.connectivity));
+            }
+            Controls::CycleLineMode => {
+                line_assist.mode = line_assist.mode.next();
+                status.info(format!("Drawing {} lines", line_assist.mode));
+            }
             Controls::SelectMode => controls.mode = ControlMode::Select,
             Controls::MoveMode => controls.mode = ControlMode::Move,
             Controls::Unselect => {
@@ -125,8 +180,157 @@ pub fn handle_input(
     }
 }
 
+/// Draws a wire through several corners: clicks add corners, Return or a double click applies
+/// the current mode along the whole path as one edit.
+pub fn path_tool(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse_button: Res<Input<MouseButton>>,
+    mouse_position: Res<MousePosition>,
+    controls: Res<Controls>,
+    line_assist: Res<LineAssist>,
+    paste: Res<PastePreview>,
+    mut path: ResMut<PathTool>,
+    mut status: ResMut<Status>,
+    mut cells_action_events: EventWriter<CellsActionEvent>,
+) {
+    if Controls::ctrl(&keys) {
+        return;
+    }
+
+    if keys.just_pressed(Controls::PathTool) {
+        path.active = !path.active;
+        path.corners.clear();
+        if path.active {
+            status.info("Click to add corners, Return or double click to finish the path");
+        }
+    }
+    if !path.active {
+        if !path.cells.is_empty() {
+            path.cells.clear();
+        }
+        return;
+    }
+
+    if keys.just_pressed(Controls::Cancel) {
+        path.active = false;
+        path.corners.clear();
+        path.cells.clear();
+        return;
+    }
+    if keys.just_pressed(Controls::RemoveCorner) {
+        path.corners.pop();
+    }
+
+    // The next corner, snapped to the line mode from the last one
+    let end = match path.corners.last() {
+        Some(last) => line_assist.mode.snap(*last, mouse_position.cell),
+        None => mouse_position.cell,
+    };
+
+    let mut finish = keys.just_pressed(Controls::FinishPath);
+    if mouse_button.just_pressed(Controls::MousePrimary) && !paste.active() {
+        let now = time.seconds_since_startup();
+        if path.corners.last() == Some(&end) && now - path.last_click < DOUBLE_CLICK {
+            finish = true;
+        } else {
+            path.corners.push(end);
+        }
+        path.last_click = now;
+    }
+
+    if finish && !path.corners.is_empty() {
+        if controls.mode.actionable() {
+            cells_action_events.send(CellsActionEvent {
+                cells: PathTool::cells_through(&path.corners),
+                action: controls.mode.into(),
+            });
+        } else {
+            status.error("Pick a mode to draw the path with first");
+        }
+        path.corners.clear();
+    }
+
+    let mut corners = path.corners.clone();
+    if !corners.is_empty() {
+        corners.push(end);
+    }
+    let cells = PathTool::cells_through(&corners);
+    // Only touch the path when it changes, the preview is rebuilt every time it does
+    if cells != path.cells {
+        path.cells = cells;
+    }
+}
+
+/// Routes a wire around existing circuitry: the first click picks the start, the route to the
+/// cursor is previewed and the second click places it.
+pub fn auto_route(
+    keys: Res<Input<KeyCode>>,
+    mouse_button: Res<Input<MouseButton>>,
+    mouse_position: Res<MousePosition>,
+    paste: Res<PastePreview>,
+    grid: Res<Grid>,
+    mut auto_route: ResMut<AutoRoute>,
+    mut status: ResMut<Status>,
+    mut cells_action_events: EventWriter<CellsActionEvent>,
+) {
+    if Controls::ctrl(&keys) {
+        return;
+    }
+
+    if keys.just_pressed(Controls::AutoRoute) {
+        auto_route.active = !auto_route.active;
+        auto_route.start = None;
+        auto_route.cells.clear();
+        if auto_route.active {
+            status.info("Click where the wire starts, then where it ends");
+        }
+    }
+    if !auto_route.active {
+        return;
+    }
+    if keys.just_pressed(Controls::Cancel) {
+        auto_route.active = false;
+        auto_route.start = None;
+        auto_route.cells.clear();
+        return;
+    }
+
+    let cursor = mouse_position.cell;
+    if let Some(start) = auto_route.start {
+        // Only search again when the cursor moves to another cell
+        if cursor != auto_route.end {
+            auto_route.end = cursor;
+            auto_route.cells = route::route(&grid, start, cursor).unwrap_or_default();
+        }
+    }
+
+    if !mouse_button.just_pressed(Controls::MousePrimary) || paste.active() {
+        return;
+    }
+    match auto_route.start {
+        None => {
+            auto_route.start = Some(cursor);
+            auto_route.end = cursor;
+            auto_route.cells = vec![cursor];
+        }
+        Some(_) if auto_route.cells.is_empty() => {
+            status.error(format!(
+                "No route keeping clear of other cells within {} cells",
+                MARGIN
+            ));
+        }
+        Some(_) => {
+            cells_action_events.send(CellsActionEvent {
+                cells: std::mem::take(&mut auto_route.cells),
+                action: AreaAction::Place,
+            });
+            auto_route.start = None;
+        }
+    }
+}
+
 pub fn edit_wire(
-    mut commands: Commands,
     mouse_position: Res<MousePosition>,
     mouse_button: Res<Input<MouseButton>>,
     keys: Res<Input<KeyCode>>,
@@ -134,120 +338,76 @@ pub fn edit_wire(
     line_assist: Res<LineAssist>,
     controls: Res<Controls>,
     cursor: Res<Cursor>,
+    paste: Res<PastePreview>,
+    path: Res<PathTool>,
+    auto_route: Res<AutoRoute>,
     mut area_action_events: EventReader<AreaActionEvent>,
-    mut tile_query: Query<(&CurrentState, &mut NextState)>,
-    mut tilemap_query: Query<(Entity, &mut TileStorage)>,
+    mut cells_action_events: EventReader<CellsActionEvent>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History>,
 ) {
     if selection.selecting || line_assist.drawing {
         return;
     }
 
-    let (tilemap_entity, mut tile_storage) = tilemap_query.single_mut();
-
     for event in area_action_events.iter() {
-        turn_cells(
-            event.area,
-            &mut commands,
-            tilemap_entity,
-            &mut tile_storage,
-            &mut tile_query,
-            event.action.into(),
-        )
+        history.begin();
+        turn_cells(event.area, &mut grid, &mut history, event.action.into());
+        history.end();
+    }
+
+    for event in cells_action_events.iter() {
+        history.begin();
+        for position in event.cells.iter() {
+            turn_cell(*position, &mut grid, &mut history, event.action.into());
+        }
+        history.end();
     }
 
-    if controls.mode.actionable() {
+    let drawing = mouse_button.any_pressed([Controls::MousePrimary, Controls::MouseSecondary]);
+    let tool_active = paste.active() || path.active || auto_route.active;
+    if controls.mode.actionable() && !tool_active && drawing {
+        // A stroke is undone as a whole
+        history.begin();
         if mouse_button.pressed(Controls::MousePrimary) {
             turn_cell(
-                TilePos {
-                    x: mouse_position.cell.x as u32,
-                    y: mouse_position.cell.y as u32,
-                },
-                &mut commands,
-                tilemap_entity,
-                &mut tile_storage,
-                &mut tile_query,
+                mouse_position.cell,
+                &mut grid,
+                &mut history,
                 controls.mode.into(),
             );
         } else if mouse_button.pressed(Controls::MouseSecondary) {
             turn_cell(
-                TilePos {
-                    x: mouse_position.cell.x as u32,
-                    y: mouse_position.cell.y as u32,
-                },
-                &mut commands,
-                tilemap_entity,
-                &mut tile_storage,
-                &mut tile_query,
+                mouse_position.cell,
+                &mut grid,
+                &mut history,
                 CellAction::Delete,
             );
         }
+    } else if !drawing {
+        history.end();
     }
 }
 
-fn turn_cells(
-    area: Area,
-    commands: &mut Commands,
-    tilemap_entity: Entity,
-    tile_storage: &mut TileStorage,
-    tile_query: &mut Query<(&CurrentState, &mut NextState)>,
-    action: CellAction,
-) {
-    let map_area = Area::new((0, 0), (SIZE, SIZE));
-    let clamped = area.clamp(map_area);
-    for x in clamped.a.x..clamped.b.x {
-        for y in clamped.a.y..clamped.b.y {
-            turn_cell(
-                TilePos::new(x as u32, y as u32),
-                commands,
-                tilemap_entity,
-                tile_storage,
-                tile_query,
-                action,
-            );
+fn turn_cells(area: Area, grid: &mut Grid, history: &mut History, action: CellAction) {
+    for x in area.a.x..area.b.x {
+        for y in area.a.y..area.b.y {
+            turn_cell((x, y).into(), grid, history, action);
         }
     }
 }
 
-fn turn_cell(
-    tile_pos: TilePos,
-    commands: &mut Commands,
-    tilemap_entity: Entity,
-    tile_storage: &mut TileStorage,
-    tile_query: &mut Query<(&CurrentState, &mut NextState)>,
-    action: CellAction,
-) {
-    if tile_pos.x >= SIZE as u32 || tile_pos.y >= SIZE as u32 {
-        return;
-    }
-
-    let tile_entity = tile_storage.get(&tile_pos);
-    if tile_entity == None {
-        if action == CellAction::Place {
-            spawn_cell(
-                commands,
-                tilemap_entity,
-                tile_storage,
-                tile_pos,
-                CellState::Conductor,
-            );
-        }
-    } else {
-        let tile_entity = tile_entity.unwrap();
-        let (current_state, mut next_state) = tile_query.get_mut(tile_entity).unwrap();
+fn turn_cell(position: Position, grid: &mut Grid, history: &mut History, action: CellAction) {
+    let rule = grid.rule();
+    let current_state = grid.get(position);
 
-        if action == CellAction::Place && current_state.0 != CellState::Conductor {
-            next_state.0 = CellState::Conductor;
-        } else if action == CellAction::Energize && current_state.0 == CellState::Conductor {
-            next_state.0 = CellState::Head;
-        } else if action == CellAction::Delete {
-            commands.entity(tile_entity).despawn();
-            tile_storage.remove(&tile_pos);
-        } else if action == CellAction::Drain
-            && (current_state.0 == CellState::Head || current_state.0 == CellState::Tail)
-        {
-            next_state.0 = CellState::Conductor;
-        }
-    }
+    let state = match action {
+        CellAction::Place => rule.placed(),
+        CellAction::Energize => rule.energized(current_state),
+        CellAction::Delete => CellState::Empty,
+        CellAction::Drain => rule.drained(current_state),
+    };
+    history.set(grid, position, state);
 }
 
 pub fn spawn_cell(
@@ -255,6 +415,7 @@ pub fn spawn_cell(
     tilemap_entity: Entity,
     tile_storage: &mut TileStorage,
     tile_pos: TilePos,
+    position: Position,
     state: CellState,
 ) {
     //info!("Spawning {:?} at {}:{}", state, tile_pos.x, tile_pos.y);
@@ -268,6 +429,7 @@ pub fn spawn_cell(
         })
         .insert(NextState(state))
         .insert(CurrentState(state))
+        .insert(position)
         .id();
     tile_storage.set(&tile_pos, entity);
 }
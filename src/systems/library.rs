@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::selection::Selection,
+    resources::{
+        clipboard::PastePreview,
+        controls::Controls,
+        library::{Library, LibraryEntry},
+        status::Status,
+    },
+    simulation::{grid::Grid, pattern::Pattern, prefab::Prefab},
+};
+
+use super::setup::PaletteMarker;
+
+/// Picks the part at this index of the [`Library`].
+#[derive(Component)]
+pub struct PaletteEntryButton(usize);
+
+/// Changes the period of the clocks in the palette by this much.
+#[derive(Component)]
+pub struct ClockPeriodButton(i32);
+
+/// Saves the selection as a new part of the library.
+pub fn library_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    selection: Res<Selection>,
+    grid: Res<Grid>,
+    mut library: ResMut<Library>,
+    mut status: ResMut<Status>,
+) {
+    if !Controls::ctrl(&keys) || !keys.just_pressed(Controls::AddToLibrary) {
+        return;
+    }
+
+    if !selection.active {
+        status.error("Select an area to add to the library first");
+        return;
+    }
+    let pattern = Pattern::from_grid(&grid, selection.area);
+    match library.add(pattern, grid.rule()) {
+        Ok(name) => status.info(format!("Added the selection to the library as {}", name)),
+        Err(e) => {
+            error!("Failed to save part: {}", e);
+            status.error(format!("Could not add the selection to the library: {}", e));
+        }
+    }
+}
+
+/// Rebuilds the buttons of the palette whenever the library changes.
+pub fn update_palette(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    library: Res<Library>,
+    palette_query: Query<Entity, With<PaletteMarker>>,
+) {
+    if !library.is_changed() {
+        return;
+    }
+
+    let palette = palette_query.single();
+    commands.entity(palette).despawn_descendants();
+
+    let text_style = TextStyle {
+        font: asset_server.load("Roboto-Regular.ttf"),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+    let button_style = Style {
+        padding: UiRect::all(Val::Px(4.)),
+        margin: UiRect::all(Val::Px(1.)),
+        ..default()
+    };
+    let button = || ButtonBundle {
+        style: button_style.clone(),
+        color: Color::rgba(0.2, 0.2, 0.2, 0.8).into(),
+        ..default()
+    };
+
+    commands.entity(palette).with_children(|palette| {
+        for (i, entry) in library.entries.iter().enumerate() {
+            palette
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn_bundle(button())
+                        .insert(PaletteEntryButton(i))
+                        .with_children(|button| {
+                            button.spawn_bundle(TextBundle::from_section(
+                                entry.name(),
+                                text_style.clone(),
+                            ));
+                        });
+
+                    if let LibraryEntry::Builtin(Prefab::Clock(_)) = entry {
+                        for (label, by) in [("-", -1), ("+", 1)] {
+                            row.spawn_bundle(button())
+                                .insert(ClockPeriodButton(by))
+                                .with_children(|button| {
+                                    button.spawn_bundle(TextBundle::from_section(
+                                        label,
+                                        text_style.clone(),
+                                    ));
+                                });
+                        }
+                    }
+                });
+        }
+    });
+}
+
+/// Picks the clicked part of the palette into the paste preview, or changes the clock period.
+pub fn palette_buttons(
+    entry_query: Query<(&Interaction, &PaletteEntryButton), Changed<Interaction>>,
+    period_query: Query<(&Interaction, &ClockPeriodButton), Changed<Interaction>>,
+    grid: Res<Grid>,
+    mut library: ResMut<Library>,
+    mut paste: ResMut<PastePreview>,
+    mut selection: ResMut<Selection>,
+    mut status: ResMut<Status>,
+) {
+    for (interaction, PaletteEntryButton(i)) in entry_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let entry = &library.entries[*i];
+        match entry.rule() {
+            Some(rule) if rule != grid.rule() => {
+                status.error(format!(
+                    "{} is a {} part, this world runs {}",
+                    entry.name(),
+                    rule.name(),
+                    grid.rule().name()
+                ));
+            }
+            _ => {
+                selection.active = false;
+                paste.pattern = Some(entry.pattern());
+                status.info(format!("Placing {}, R rotates it", entry.name()));
+            }
+        }
+    }
+
+    for (interaction, ClockPeriodButton(by)) in period_query.iter() {
+        if *interaction == Interaction::Clicked {
+            library.change_clock_period(*by);
+        }
+    }
+}
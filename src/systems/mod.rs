@@ -1,5 +1,12 @@
+pub mod breakpoints;
 pub mod camera;
+pub mod clipboard;
+pub mod clock;
+pub mod file;
+pub mod history;
 pub mod input;
+pub mod library;
+pub mod probes;
 pub mod setup;
 pub mod state;
 pub mod ui;
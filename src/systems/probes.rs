@@ -0,0 +1,353 @@
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::{
+    components::{cell_state::CellState, colors::Colors, position::Position},
+    formats::vcd,
+    resources::{
+        controls::Controls,
+        file_prompt::{FileAction, FileChosen, FilePrompt},
+        status::Status,
+        waveform::Waveform,
+    },
+    simulation::{grid::Grid, probes::Probes, rule::Rule},
+    utils::cell_to_world,
+    CELL_SIZE,
+};
+
+use super::{
+    camera::MousePosition,
+    file::ask_for_file,
+    setup::{ProbeMarkersMarker, WaveformMarker},
+};
+
+/// Height of the trace of a probe in pixels.
+const ROW_HEIGHT: f32 = 20.;
+/// Width of the column of probe names in pixels.
+const NAME_WIDTH: f32 = 120.;
+/// Least distance between the labels of the generation axis in pixels.
+const TICK_SPACING: f32 = 80.;
+
+/// Pins or removes probes, zooms the waveform and exports it.
+pub fn probe_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mouse_position: Res<MousePosition>,
+    grid: Res<Grid>,
+    mut probes: ResMut<Probes>,
+    mut waveform: ResMut<Waveform>,
+    mut prompt: ResMut<FilePrompt>,
+    mut chosen: EventWriter<FileChosen>,
+    mut status: ResMut<Status>,
+) {
+    if Controls::ctrl(&keys) {
+        if keys.just_pressed(Controls::ExportProbes) {
+            if probes.is_empty() {
+                status.error("Pin a probe on a cell to export first");
+            } else {
+                ask_for_file(FileAction::ExportProbes, None, &mut prompt, &mut chosen);
+            }
+        }
+        return;
+    }
+
+    if keys.just_pressed(Controls::ToggleProbe) {
+        let p = mouse_position.cell;
+        if probes.toggle(p) {
+            probes.record(&grid);
+            status.info(format!("Probing cell {}, {}", p.x, p.y));
+        } else {
+            status.info(format!("Removed the probe on cell {}, {}", p.x, p.y));
+        }
+    } else if keys.just_pressed(Controls::WaveformZoomIn) {
+        waveform.zoom_in();
+    } else if keys.just_pressed(Controls::WaveformZoomOut) {
+        waveform.zoom_out();
+    }
+}
+
+/// Writes the probes to the file chosen for them.
+pub fn export_probes(
+    mut chosen: EventReader<FileChosen>,
+    grid: Res<Grid>,
+    probes: Res<Probes>,
+    mut status: ResMut<Status>,
+) {
+    for FileChosen { action, path } in chosen.iter() {
+        if *action != FileAction::ExportProbes {
+            continue;
+        }
+        match fs::write(path, vcd::write(&probes, grid.rule())) {
+            Ok(()) => status.info(format!("Exported probes to {}", path.display())),
+            Err(e) => {
+                error!("Failed to export {}: {}", path.display(), e);
+                status.error(format!("Could not export {}: {}", path.display(), e));
+            }
+        }
+    }
+}
+
+/// Samples the probes once the frame's edits, jumps and steps back are done. Generations run by
+/// the simulation are sampled as they are stepped.
+pub fn record_probes(grid: Res<Grid>, mut probes: ResMut<Probes>) {
+    if !probes.is_current(&grid) {
+        probes.record(&grid);
+    }
+}
+
+/// Marks the probed cells in the world.
+pub fn update_probe_markers(
+    mut commands: Commands,
+    probes: Res<Probes>,
+    grid: Res<Grid>,
+    mut shown: Local<Vec<Vec2>>,
+    markers_query: Query<Entity, With<ProbeMarkersMarker>>,
+) {
+    let neighbourhood = grid.topology().neighbourhood;
+    let positions: Vec<Vec2> = probes
+        .probes
+        .iter()
+        .map(|probe| cell_to_world(probe.position, neighbourhood))
+        .collect();
+    if *shown == positions {
+        return;
+    }
+
+    let markers = markers_query.single();
+    commands.entity(markers).despawn_descendants();
+    commands.entity(markers).with_children(|parent| {
+        for p in positions.iter() {
+            parent.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Colors::Probe,
+                    custom_size: Some(Vec2::splat(CELL_SIZE * 1.5)),
+                    ..default()
+                },
+                transform: Transform::from_translation(p.extend(0.)),
+                ..default()
+            });
+        }
+    });
+    *shown = positions;
+}
+
+/// How high the trace of a cell in `state` is drawn, as a fraction of the row.
+fn level(rule: Rule, state: CellState) -> f32 {
+    match (rule, state) {
+        (_, CellState::Empty) => 0.,
+        (Rule::Wireworld, CellState::Head) => 1.,
+        (Rule::Wireworld, CellState::Tail) => 0.5,
+        (Rule::Wireworld, _) => 0.1,
+        (Rule::Generations { .. }, CellState::Alive) => 1.,
+        (Rule::Generations { .. }, _) => 0.5,
+    }
+}
+
+/// Spacing of the labels of the generation axis, 1, 2 or 5 times a power of ten generations.
+fn tick_step(zoom: f32) -> u64 {
+    let mut step = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            if (step * factor) as f32 * zoom >= TICK_SPACING {
+                return step * factor;
+            }
+        }
+        step *= 10;
+    }
+}
+
+/// What the waveform panel shows, so new generations only add on to it.
+#[derive(Default)]
+pub struct ShownWaveform {
+    probes: Vec<Position>,
+    zoom: f32,
+    rule: Option<Rule>,
+    /// Generation at the left of the panel, which only moves once the traces reach its right.
+    first: u64,
+    /// Last generation drawn, `None` before any.
+    last: Option<u64>,
+    /// Pixel columns drawn on every track.
+    columns: usize,
+    tracks: Vec<Entity>,
+    axis: Option<Entity>,
+}
+
+/// Draws the generations sampled by each probe, one row per probe above the generation axis.
+///
+/// New generations are added to the right of the traces. The panel is only drawn again when
+/// the probes, the zoom or the rule change, when going back, or once the traces reach its
+/// right, the newest generation then being put half way across.
+pub fn update_waveform(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    probes: Res<Probes>,
+    waveform: Res<Waveform>,
+    grid: Res<Grid>,
+    mut shown: Local<ShownWaveform>,
+    mut panel_query: Query<(Entity, &mut Style), With<WaveformMarker>>,
+) {
+    if !probes.is_changed() && !waveform.is_changed() {
+        return;
+    }
+
+    let (panel, mut style) = panel_query.single_mut();
+    let span = match probes.span() {
+        Some(span) => span,
+        None => {
+            commands.entity(panel).despawn_descendants();
+            style.display = Display::None;
+            *shown = ShownWaveform::default();
+            return;
+        }
+    };
+    style.display = Display::Flex;
+
+    let rule = grid.rule();
+    let width = Waveform::Width as usize;
+    let last = span.1;
+    let positions: Vec<Position> = probes.probes.iter().map(|p| p.position).collect();
+    let adds_on = shown.last.map_or(false, |drawn| last > drawn)
+        && last < shown.first + waveform.visible()
+        && shown.probes == positions
+        && shown.zoom == waveform.zoom
+        && shown.rule == Some(rule);
+
+    let text_style = TextStyle {
+        font: asset_server.load("Roboto-Regular.ttf"),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+    let at = |left: f32, bottom: f32, size: Size<Val>| Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Px(left),
+            bottom: Val::Px(bottom),
+            ..default()
+        },
+        size,
+        ..default()
+    };
+
+    if !adds_on {
+        commands.entity(panel).despawn_descendants();
+        *shown = ShownWaveform {
+            probes: positions,
+            zoom: waveform.zoom,
+            rule: Some(rule),
+            first: (last + 1).saturating_sub(waveform.visible() / 2),
+            ..default()
+        };
+
+        let row = |height: f32| NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(NAME_WIDTH + Waveform::Width), Val::Px(height)),
+                margin: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        };
+        let track = || NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(Waveform::Width), Val::Percent(100.)),
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        };
+        let name = |text: String| {
+            TextBundle::from_section(text, text_style.clone()).with_style(Style {
+                size: Size::new(Val::Px(NAME_WIDTH), Val::Auto),
+                ..default()
+            })
+        };
+
+        let mut tracks = Vec::new();
+        let mut axis = None;
+        commands.entity(panel).with_children(|panel| {
+            for probe in probes.probes.iter() {
+                panel.spawn_bundle(row(ROW_HEIGHT)).with_children(|row| {
+                    row.spawn_bundle(name(format!(
+                        "{} ({}, {})",
+                        probe.name, probe.position.x, probe.position.y
+                    )));
+                    tracks.push(row.spawn_bundle(track()).id());
+                });
+            }
+
+            // Generation axis
+            panel.spawn_bundle(row(ROW_HEIGHT)).with_children(|row| {
+                row.spawn_bundle(name("Generation".to_string()));
+                axis = Some(row.spawn_bundle(track()).id());
+            });
+        });
+        shown.tracks = tracks;
+        shown.axis = axis;
+    }
+
+    let first = shown.first;
+    let x_of = |generation: u64| ((generation - first) as f32 * waveform.zoom) as usize;
+    // Only whole columns are drawn, the one the last generation is part of may still change
+    let (start, end) = (shown.columns, x_of(last + 1).min(width));
+
+    for (probe, track) in probes.probes.iter().zip(shown.tracks.iter()) {
+        // The state drawn in each pixel column, the highest one where several generations
+        // share a column, so short pulses stay visible when zoomed out
+        let mut columns: Vec<Option<CellState>> = vec![None; width];
+        let samples: Vec<&(u64, CellState)> = probe.samples.iter().collect();
+        for (i, (generation, state)) in samples.iter().enumerate() {
+            let until = samples.get(i + 1).map_or(last + 1, |next| next.0);
+            if until <= first {
+                continue;
+            }
+            let from = x_of((*generation).max(first));
+            let to = x_of(until).max(from + 1).min(width);
+            for column in columns[from.min(width)..to].iter_mut() {
+                if column.map_or(true, |c| level(rule, *state) > level(rule, c)) {
+                    *column = Some(*state);
+                }
+            }
+        }
+
+        commands.entity(*track).with_children(|track| {
+            let mut x = start;
+            while x < end {
+                let state = columns[x];
+                let run = columns[x..end].iter().take_while(|c| **c == state).count();
+                if let Some(state) = state.filter(|s| *s != CellState::Empty) {
+                    let height = level(rule, state) * ROW_HEIGHT;
+                    track.spawn_bundle(NodeBundle {
+                        style: at(
+                            x as f32,
+                            0.,
+                            Size::new(Val::Px(run as f32), Val::Px(height)),
+                        ),
+                        color: Colors::of(rule, state).into(),
+                        ..default()
+                    });
+                }
+                x += run;
+            }
+        });
+    }
+
+    if let Some(axis) = shown.axis {
+        commands.entity(axis).with_children(|axis| {
+            let step = tick_step(waveform.zoom);
+            let after = shown.last.map_or(first, |drawn| drawn + 1);
+            let mut tick = (after + step - 1) / step * step;
+            while tick <= last {
+                axis.spawn_bundle(
+                    TextBundle::from_section(tick.to_string(), text_style.clone()).with_style(at(
+                        x_of(tick) as f32,
+                        0.,
+                        Size::new(Val::Auto, Val::Auto),
+                    )),
+                );
+                tick += step;
+            }
+        });
+    }
+    shown.columns = end;
+    shown.last = Some(last);
+}
@@ -1,14 +1,9 @@
 use bevy::{prelude::*, sprite::Anchor, window::WindowId, winit::WinitWindows};
-use bevy_ecs_tilemap::{
-    prelude::{TilemapSize, TilemapTexture, TilemapTileSize, TilemapType},
-    tiles::TileStorage,
-    TilemapBundle,
-};
 
 use crate::{
     components::colors::Colors,
-    resources::{cursor::CursorMarker, gui::Gui},
-    CELL_SIZE, SIZE,
+    resources::{chunk_maps::ChunkMaps, cursor::CursorMarker, gui::Gui},
+    CELL_SIZE,
 };
 
 pub fn setup(
@@ -22,42 +17,11 @@ pub fn setup(
     let primary = windows.get_window(WindowId::primary()).unwrap();
     primary.set_title("Electric Rust");
 
-    let tilemap_entity = commands.spawn().id();
-
-    let tilemap_size = TilemapSize {
-        x: SIZE as u32,
-        y: SIZE as u32,
-    };
-    let tile_storage = TileStorage::empty(tilemap_size);
-    let tile_size = TilemapTileSize { x: 4., y: 4. };
-    let grid_size = tile_size.into();
-
-    let texture_handle = asset_server.load("tile.png");
-
-    commands
-        .entity(tilemap_entity)
-        .insert_bundle(TilemapBundle {
-            grid_size,
-            size: tilemap_size,
-            storage: tile_storage,
-            texture: TilemapTexture::Single(texture_handle),
-            tile_size,
-            map_type: TilemapType::Square {
-                diagonal_neighbors: true,
-            },
-            ..default()
-        });
+    commands.insert_resource(ChunkMaps::new(asset_server.load("tile.png")));
 
     // Spawn main camera
     commands.spawn_bundle(Camera2dBundle {
-        transform: Transform {
-            translation: Vec3 {
-                x: (SIZE as f32 * CELL_SIZE - CELL_SIZE) / 2.,
-                y: (SIZE as f32 * CELL_SIZE - CELL_SIZE) / 2.,
-                z: 999.9,
-            },
-            ..default()
-        },
+        transform: Transform::from_xyz(0., 0., 999.9),
         ..default()
     });
 
@@ -130,6 +94,75 @@ pub fn setup(
                 .insert(PauseIconMarker)
                 .id();
 
+            // Rule, world and speed
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: roboto_font_handle.clone(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(8.0),
+                            top: Val::Px(8.0),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                )
+                .insert(WorldTextMarker);
+
+            // Status line
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: roboto_font_handle.clone(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(8.0),
+                            top: Val::Px(32.0),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                )
+                .insert(StatusTextMarker);
+
+            // Statistics, below the pause icon
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: roboto_font_handle.clone(),
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            right: Val::Px(8.0),
+                            top: Val::Px(80.0),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                )
+                .insert(StatsTextMarker);
+
             // Toolbar
             parent
                 .spawn_bundle(NodeBundle {
@@ -191,40 +224,71 @@ pub fn setup(
 
     let font_handle: Handle<Font> = asset_server.load("font.ttf");
 
-    // Spawn line assist overlay
+    // Spawn the palette of parts, its buttons are added by `update_palette`
     commands
-        .spawn()
-        .insert_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: Colors::DragOverlayPlace,
-                anchor: Anchor::BottomLeft,
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(8.0),
+                    top: Val::Px(64.0),
+                    ..default()
+                },
                 ..default()
             },
-            visibility: Visibility { is_visible: false },
+            color: Color::NONE.into(),
             ..default()
         })
-        .insert(LineAssistOverlayMarker)
-        .with_children(|parent| {
-            parent
-                .spawn()
-                .insert_bundle(Text2dBundle::default())
-                .insert(DragTextHorizontalMarker);
+        .insert(PaletteMarker);
 
-            parent
-                .spawn()
-                .insert_bundle(Text2dBundle::default())
-                .insert(DragTextHorizontalMarker);
+    // Spawn the waveform panel of the probes, its rows are added by `update_waveform`
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                flex_direction: FlexDirection::ColumnReverse,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(8.0),
+                    bottom: Val::Px(88.0),
+                    ..default()
+                },
+                padding: UiRect::all(Val::Px(4.)),
+                ..default()
+            },
+            color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            ..default()
+        })
+        .insert(WaveformMarker);
 
-            parent
-                .spawn()
-                .insert_bundle(Text2dBundle::default())
-                .insert(DragTextVerticalMarker);
+    // Spawn the markers of probed cells, added as children by `update_probe_markers`
+    commands
+        .spawn_bundle(TransformBundle::from_transform(Transform::from_xyz(
+            0.,
+            0.,
+            16. * CELL_SIZE,
+        )))
+        .insert(ProbeMarkersMarker);
 
-            parent
-                .spawn()
-                .insert_bundle(Text2dBundle::default())
-                .insert(DragTextVerticalMarker);
-        });
+    // Spawn the markers of breakpoints, added as children by `update_breakpoint_markers`
+    commands
+        .spawn_bundle(TransformBundle::from_transform(Transform::from_xyz(
+            0.,
+            0.,
+            16. * CELL_SIZE,
+        )))
+        .insert(BreakpointMarkersMarker);
+
+    // Spawn line assist overlay, its cells are added as children while drawing a line
+    commands
+        .spawn_bundle(TransformBundle::from_transform(Transform::from_xyz(
+            0.,
+            0.,
+            16. * CELL_SIZE,
+        )))
+        .insert(LineAssistOverlayMarker);
 
     // Spawn area selection overlay
     commands
@@ -261,6 +325,26 @@ pub fn setup(
                 .insert(DragTextVerticalMarker);
         });
 
+    // Spawn the four edges of bounded worlds, placed by `update_world_edges`
+    for _ in 0..4 {
+        commands
+            .spawn()
+            .insert_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Colors::WorldEdge,
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(WorldEdgeMarker);
+    }
+
+    // Spawn paste preview, its cells are added as children while pasting
+    commands
+        .spawn_bundle(TransformBundle::default())
+        .insert(PastePreviewMarker);
+
     gui.highlighted_toolbar_icon = gui.move_icon;
     commands.insert_resource(gui);
 }
@@ -277,5 +361,32 @@ pub struct LineAssistOverlayMarker;
 #[derive(Component)]
 pub struct SelectionOverlayMarker;
 
+#[derive(Component)]
+pub struct PastePreviewMarker;
+
+#[derive(Component)]
+pub struct WorldEdgeMarker;
+
+#[derive(Component)]
+pub struct PaletteMarker;
+
+#[derive(Component)]
+pub struct WaveformMarker;
+
+#[derive(Component)]
+pub struct ProbeMarkersMarker;
+
+#[derive(Component)]
+pub struct BreakpointMarkersMarker;
+
 #[derive(Component)]
 pub struct PauseIconMarker;
+
+#[derive(Component)]
+pub struct StatusTextMarker;
+
+#[derive(Component)]
+pub struct WorldTextMarker;
+
+#[derive(Component)]
+pub struct StatsTextMarker;
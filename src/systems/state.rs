@@ -1,61 +1,607 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
 use bevy::prelude::*;
 use bevy_ecs_tilemap::{
-    prelude::{get_tile_neighbors, TilemapType},
+    prelude::{TilemapSize, TilemapTexture, TilemapTileSize, TilemapType},
     tiles::{TileColor, TilePos, TileStorage},
+    TilemapBundle,
 };
 
-use crate::components::{
-    cell_state::{CellState, CurrentState, NextState},
-    colors::Colors,
+use crate::{
+    components::{
+        cell_state::{CellState, CurrentState, NextState},
+        colors::Colors,
+        position::Position,
+        selection::Selection,
+    },
+    resources::{
+        chunk_maps::ChunkMaps, controls::Controls, jump::Jump, period_search::PeriodSearch,
+        speed::Speed, status::Status, stepping::Stepping,
+    },
+    simulation::{
+        breakpoints::{Breakpoints, Hit},
+        grid::{chunk_of, local_of, Grid, CHUNK_SIZE},
+        hashlife::HashLife,
+        heatmap::{Heatmap, WINDOW},
+        history::History,
+        period::{self, Progress},
+        probes::Probes,
+        rewind::Rewind,
+        topology::Neighbourhood,
+    },
+    utils::cell_to_world,
+    AppState, CELL_SIZE,
 };
 
+use super::input::spawn_cell;
+
 pub fn update_state(
+    grid: Res<Grid>,
     mut tile_query: Query<(&mut CurrentState, &NextState, &mut TileColor), Changed<NextState>>,
 ) {
     for (mut current_state, next_state, mut tile_color) in tile_query.iter_mut() {
-        match next_state.0 {
-            CellState::Conductor => tile_color.0 = Colors::Conductor,
-            CellState::Tail => tile_color.0 = Colors::Tail,
-            CellState::Head => tile_color.0 = Colors::Head,
-            CellState::Empty => {}
+        if next_state.0 != CellState::Empty {
+            tile_color.0 = Colors::of(grid.rule(), next_state.0);
         }
         current_state.0 = next_state.0;
     }
 }
 
+/// Blends the heat of cells over their colors while the heatmap is shown. Only the tiles whose
+/// heat changed are recolored, along with the ones [`update_state`] just gave a plain color.
+/// Hiding the heatmap clears it, which puts the plain colors back.
+pub fn update_heatmap(
+    grid: Res<Grid>,
+    mut heatmap: ResMut<Heatmap>,
+    chunk_maps: Res<ChunkMaps>,
+    tilemap_query: Query<&TileStorage>,
+    changed_query: Query<Entity, Changed<NextState>>,
+    mut tile_query: Query<(&Position, &CurrentState, &mut TileColor)>,
+) {
+    let mut tiles: Vec<Entity> = heatmap
+        .take_changes()
+        .into_iter()
+        .filter_map(|p| {
+            let (key, tile_pos) = tilemap_of(p, chunk_maps.hexagonal);
+            let tile_storage = tilemap_query.get(*chunk_maps.maps.get(&key)?).ok()?;
+            tile_storage.get(&tile_pos)
+        })
+        .collect();
+    if heatmap.enabled {
+        tiles.extend(changed_query.iter());
+    }
+
+    for tile_entity in tiles {
+        if let Ok((p, current_state, mut tile_color)) = tile_query.get_mut(tile_entity) {
+            let mut color = Colors::of(grid.rule(), current_state.0);
+            if heatmap.enabled {
+                color = Colors::heat(color, heatmap.heat(*p));
+            }
+            // Only touch colors that change, so unchanged tiles are not sent to the GPU again
+            if tile_color.0 != color {
+                tile_color.0 = color;
+            }
+        }
+    }
+}
+
+/// Shows or hides the heatmap of where heads have been.
+pub fn heatmap_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut heatmap: ResMut<Heatmap>,
+    mut status: ResMut<Status>,
+) {
+    if Controls::ctrl(&keys) || !keys.just_pressed(Controls::Heatmap) {
+        return;
+    }
+
+    heatmap.toggle();
+    if heatmap.enabled {
+        status.info(format!(
+            "Showing how often cells were heads over the last {} generations",
+            WINDOW
+        ));
+    } else {
+        status.info("Heatmap hidden");
+    }
+}
+
+/// Time per frame the simulation may take before it falls behind its target speed.
+const STEP_BUDGET: Duration = Duration::from_millis(12);
+
+/// Advances the grid by one generation, recording it for stepping back, the probes and the
+/// heatmap. Returns the breakpoint it set off, if any.
+fn step(
+    grid: &mut Grid,
+    rewind: &mut Rewind,
+    probes: &mut Probes,
+    heatmap: &mut Heatmap,
+    breakpoints: &mut Breakpoints,
+) -> Option<Hit> {
+    let seen = grid.changes().len();
+    rewind.step(grid);
+    probes.record(grid);
+    let changed = &grid.changes()[seen..];
+    heatmap.record(grid, changed);
+    breakpoints.check(grid, changed)
+}
+
+/// Pauses the simulation on the generation a breakpoint fired in.
+fn break_at(hit: Hit, app_state: &mut AppState, status: &mut Status) {
+    *app_state = AppState::Paused;
+    status.info(format!(
+        "Paused at generation {}: {}",
+        hit.generation, hit.breakpoint
+    ));
+}
+
+/// Runs as many generations as the speed asks for since the last frame, or as many as fit
+/// in the frame budget when uncapped. Stops early when a breakpoint fires.
 pub fn do_state(
+    time: Res<Time>,
+    mut app_state: ResMut<AppState>,
+    mut status: ResMut<Status>,
+    mut speed: ResMut<Speed>,
+    mut grid: ResMut<Grid>,
+    mut rewind: ResMut<Rewind>,
+    mut probes: ResMut<Probes>,
+    mut heatmap: ResMut<Heatmap>,
+    mut breakpoints: ResMut<Breakpoints>,
+) {
+    let start = Instant::now();
+    let mut generations = 0u64;
+    let mut heads = 0u64;
+    let mut hit = None;
+
+    match speed.target() {
+        Some(target) => {
+            speed.accumulator += time.delta_seconds_f64() * target;
+            while speed.accumulator >= 1. && start.elapsed() < STEP_BUDGET && hit.is_none() {
+                hit = step(
+                    &mut grid,
+                    &mut rewind,
+                    &mut probes,
+                    &mut heatmap,
+                    &mut breakpoints,
+                );
+                speed.accumulator -= 1.;
+                generations += 1;
+                heads += grid.count(CellState::Head);
+            }
+            // Drop what did not fit in the budget instead of trying to catch up forever
+            speed.accumulator = speed.accumulator.min(1.);
+        }
+        None => {
+            while start.elapsed() < STEP_BUDGET && hit.is_none() {
+                hit = step(
+                    &mut grid,
+                    &mut rewind,
+                    &mut probes,
+                    &mut heatmap,
+                    &mut breakpoints,
+                );
+                generations += 1;
+                heads += grid.count(CellState::Head);
+            }
+        }
+    }
+
+    let delta = time.delta_seconds_f64();
+    if delta > 0. {
+        speed.measured = speed.measured * 0.9 + generations as f64 / delta * 0.1;
+        speed.measured_heads = speed.measured_heads * 0.9 + heads as f64 / delta * 0.1;
+    }
+    if let Some(hit) = hit {
+        break_at(hit, &mut app_state, &mut status);
+    }
+}
+
+pub fn speed_shortcuts(keys: Res<Input<KeyCode>>, mut speed: ResMut<Speed>) {
+    if Controls::ctrl(&keys) {
+        return;
+    }
+
+    if keys.just_pressed(Controls::Faster) {
+        speed.faster();
+    } else if keys.just_pressed(Controls::Slower) {
+        speed.slower();
+    }
+}
+
+pub fn step_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    app_state: Res<AppState>,
+    mut stepping: ResMut<Stepping>,
+    mut status: ResMut<Status>,
+    mut grid: ResMut<Grid>,
+    mut rewind: ResMut<Rewind>,
+) {
+    if *app_state != AppState::Paused || Controls::ctrl(&keys) {
+        return;
+    }
+
+    for key in keys.get_just_pressed() {
+        match *key {
+            Controls::Step => {
+                stepping.pending += if Controls::shift(&keys) {
+                    stepping.advance_by
+                } else {
+                    1
+                };
+            }
+            Controls::StepBack => {
+                stepping.pending = 0;
+                if !rewind.step_back(&mut grid) {
+                    status.error("No earlier generation recorded, edits and jumps clear them");
+                }
+            }
+            Controls::AdvanceMore => {
+                stepping.advance_by = (stepping.advance_by * 10).min(Stepping::MaxAdvance);
+            }
+            Controls::AdvanceLess => {
+                stepping.advance_by = (stepping.advance_by / 10).max(1);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Runs the generations requested while paused, spreading long advances over several frames.
+/// A breakpoint firing drops the rest of the advance.
+pub fn step_paused(
+    mut app_state: ResMut<AppState>,
+    mut status: ResMut<Status>,
+    mut stepping: ResMut<Stepping>,
+    mut grid: ResMut<Grid>,
+    mut rewind: ResMut<Rewind>,
+    mut probes: ResMut<Probes>,
+    mut heatmap: ResMut<Heatmap>,
+    mut breakpoints: ResMut<Breakpoints>,
+) {
+    if *app_state != AppState::Paused {
+        stepping.pending = 0;
+        return;
+    }
+
+    let start = Instant::now();
+    while stepping.pending > 0 && start.elapsed() < STEP_BUDGET {
+        stepping.pending -= 1;
+        let hit = step(
+            &mut grid,
+            &mut rewind,
+            &mut probes,
+            &mut heatmap,
+            &mut breakpoints,
+        );
+        if let Some(hit) = hit {
+            stepping.pending = 0;
+            break_at(hit, &mut app_state, &mut status);
+        }
+    }
+}
+
+/// Starts looking for after how many generations the selected cells repeat, or stops looking.
+pub fn period_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    selection: Res<Selection>,
+    grid: Res<Grid>,
+    mut period_search: ResMut<PeriodSearch>,
+    mut status: ResMut<Status>,
+) {
+    if !Controls::ctrl(&keys) || !keys.just_pressed(Controls::FindPeriod) {
+        return;
+    }
+
+    if period_search.search.take().is_some() {
+        status.info("Stopped looking for the period");
+    } else if !selection.active {
+        status.error("Select an area to find the period of first");
+    } else {
+        period_search.search = Some(period::Search::new(&grid, selection.cells()));
+        status.info("Looking for the period of the selection");
+    }
+}
+
+/// Time per frame spent looking for a period, on top of running the simulation.
+const SEARCH_BUDGET: Duration = Duration::from_millis(4);
+
+/// Runs the period search for as long as the frame budget allows, and reports what it found.
+pub fn find_period(mut period_search: ResMut<PeriodSearch>, mut status: ResMut<Status>) {
+    let search = match period_search.search.as_mut() {
+        Some(search) => search,
+        None => return,
+    };
+
+    let start = Instant::now();
+    let mut progress = search.progress();
+    while matches!(progress, Progress::Running(_)) && start.elapsed() < SEARCH_BUDGET {
+        progress = search.run(64);
+    }
+
+    match progress {
+        Progress::Running(generations) => status.info(format!(
+            "Looking for the period of the selection, {} generations so far",
+            generations
+        )),
+        Progress::Found(found) => status.info(format!(
+            "The selection repeats every {} generations, after {} generations",
+            found.period, found.pre_period
+        )),
+        Progress::NotFound => status.error(format!(
+            "The selection does not repeat within {} generations",
+            period::MAX_GENERATIONS
+        )),
+    }
+    if !matches!(progress, Progress::Running(_)) {
+        period_search.search = None;
+    }
+}
+
+/// Switches the world to the next preset rule.
+pub fn rule_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut status: ResMut<Status>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History>,
+) {
+    if Controls::ctrl(&keys) && keys.just_pressed(Controls::CycleRule) {
+        let rule = grid.rule().next_preset();
+        grid.set_rule(rule);
+        // Edits made under the old rule may no longer be valid states
+        history.clear();
+        status.info(format!("Rule: {} ({})", rule.name(), rule));
+    }
+}
+
+/// Cycles the neighbourhood, or the edges of the world. Turning edges on while an area is
+/// selected makes the selection the world.
+pub fn topology_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    selection: Res<Selection>,
+    mut status: ResMut<Status>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History>,
+) {
+    if !Controls::ctrl(&keys) {
+        return;
+    }
+
+    let mut topology = grid.topology();
+    if keys.just_pressed(Controls::CycleNeighbourhood) {
+        topology.neighbourhood = topology.neighbourhood.next();
+    } else if keys.just_pressed(Controls::CycleEdges) {
+        topology.edges = topology.edges.next();
+        let size = selection.area.size();
+        if selection.active && !selection.selecting && size.x >= 3 && size.y >= 3 {
+            topology.origin = selection.area.a;
+            topology.width = size.x;
+            topology.height = size.y;
+        }
+    } else {
+        return;
+    }
+
+    grid.set_topology(topology);
+    history.clear();
+    status.info(format!("World: {}", topology));
+}
+
+pub fn jump_generations(
+    keys: Res<Input<KeyCode>>,
+    mut jump: ResMut<Jump>,
+    mut hashlife: ResMut<HashLife>,
+    mut status: ResMut<Status>,
+    mut grid: ResMut<Grid>,
+) {
+    for key in keys.get_just_pressed() {
+        match *key {
+            Controls::Jump if !HashLife::supports(&grid) => {
+                status.error("Jumping only works in infinite worlds, Ctrl+B changes the edges");
+            }
+            Controls::Jump => match hashlife.jump(&mut grid, jump.exponent) {
+                Ok(()) => info!(
+                    "Jumped 2^{} generations to generation {}",
+                    jump.exponent,
+                    grid.generation()
+                ),
+                Err(e) => status.error(format!("Could not jump 2^{}: {}", jump.exponent, e)),
+            },
+            Controls::JumpFurther => {
+                jump.exponent = (jump.exponent + 1).min(Jump::MaxExponent);
+                info!("Jump size 2^{}", jump.exponent);
+            }
+            Controls::JumpShorter => {
+                jump.exponent = jump.exponent.saturating_sub(1);
+                info!("Jump size 2^{}", jump.exponent);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Mirrors the cells changed in the [`Grid`] onto the tilemaps of their chunks.
+pub fn sync_tiles(
     mut commands: Commands,
-    tile_query: Query<(Entity, &TilePos, &CurrentState)>,
-    tilemap_query: Query<(&TileStorage, &TilemapType)>,
-) {
-    let (tile_storage, tilemap_type) = tilemap_query.single();
-
-    for (entity, tile_pos, current_state) in tile_query.iter() {
-        match current_state.0 {
-            CellState::Conductor => {
-                let heads = get_tile_neighbors(tile_pos, tile_storage, tilemap_type)
-                    .into_iter()
-                    .filter(|neighbor| {
-                        let state = tile_query.get_component::<CurrentState>(*neighbor).unwrap();
-                        state.0 == CellState::Head
-                    })
-                    .count();
-                if heads == 1 || heads == 2 {
-                    // Turn on this cell
-                    commands.entity(entity).insert(NextState(CellState::Head));
+    mut grid: ResMut<Grid>,
+    mut chunk_maps: ResMut<ChunkMaps>,
+    mut tile_query: Query<&mut NextState>,
+    mut tilemap_query: Query<&mut TileStorage>,
+) {
+    // Switching between square and hexagonal layouts starts over, the grid reports every cell
+    // as changed when its topology changes
+    let hexagonal = grid.topology().neighbourhood == Neighbourhood::Hexagonal;
+    if hexagonal != chunk_maps.hexagonal {
+        for (_, tilemap_entity) in chunk_maps.maps.drain() {
+            if let Ok(tile_storage) = tilemap_query.get(tilemap_entity) {
+                for tile_entity in tile_storage.iter().flatten() {
+                    commands.entity(*tile_entity).despawn();
                 }
             }
-            CellState::Tail => {
-                // Turn of this cell
-                commands
-                    .entity(entity)
-                    .insert(NextState(CellState::Conductor));
+            commands.entity(tilemap_entity).despawn();
+        }
+        chunk_maps.hexagonal = hexagonal;
+    }
+
+    let mut changes: HashMap<Position, Vec<Position>> = HashMap::new();
+    for position in grid.take_changes() {
+        changes
+            .entry(tilemap_of(position, hexagonal).0)
+            .or_default()
+            .push(position);
+    }
+
+    for (chunk, positions) in changes {
+        match chunk_maps.maps.get(&chunk).copied() {
+            Some(tilemap_entity) => {
+                let mut tile_storage = tilemap_query.get_mut(tilemap_entity).unwrap();
+                sync_chunk(
+                    &mut commands,
+                    &grid,
+                    tilemap_entity,
+                    &mut tile_storage,
+                    &mut tile_query,
+                    positions,
+                    hexagonal,
+                );
             }
-            CellState::Head => {
-                // De-Energize this cell
-                commands.entity(entity).insert(NextState(CellState::Tail));
+            None => {
+                let tilemap_entity = commands.spawn().id();
+                let mut tile_storage = TileStorage::empty(chunk_tilemap_size(hexagonal));
+                sync_chunk(
+                    &mut commands,
+                    &grid,
+                    tilemap_entity,
+                    &mut tile_storage,
+                    &mut tile_query,
+                    positions,
+                    hexagonal,
+                );
+                spawn_chunk_tilemap(
+                    &mut commands,
+                    tilemap_entity,
+                    tile_storage,
+                    chunk_maps.texture.clone(),
+                    chunk,
+                    hexagonal,
+                );
+                chunk_maps.maps.insert(chunk, tilemap_entity);
             }
-            CellState::Empty => {}
         }
     }
 }
+
+fn sync_chunk(
+    commands: &mut Commands,
+    grid: &Grid,
+    tilemap_entity: Entity,
+    tile_storage: &mut TileStorage,
+    tile_query: &mut Query<&mut NextState>,
+    positions: Vec<Position>,
+    hexagonal: bool,
+) {
+    for position in positions {
+        let state = grid.get(position);
+        let tile_pos = tilemap_of(position, hexagonal).1;
+        match tile_storage.get(&tile_pos) {
+            Some(tile_entity) => {
+                if state == CellState::Empty {
+                    commands.entity(tile_entity).despawn();
+                    tile_storage.remove(&tile_pos);
+                } else if let Ok(mut next_state) = tile_query.get_mut(tile_entity) {
+                    next_state.0 = state;
+                }
+            }
+            None => {
+                if state != CellState::Empty {
+                    spawn_cell(
+                        commands,
+                        tilemap_entity,
+                        tile_storage,
+                        tile_pos,
+                        position,
+                        state,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The tilemap showing the cell at `p`, and the tile of the cell in it.
+///
+/// Square layouts have a tilemap per chunk. Hexagonal layouts shift every row by half a cell,
+/// which a square tilemap cannot do, so they have a tilemap per row of a chunk instead.
+fn tilemap_of(p: Position, hexagonal: bool) -> (Position, TilePos) {
+    let local = local_of(p);
+    if hexagonal {
+        let key = Position {
+            x: chunk_of(p).x,
+            y: p.y,
+        };
+        let tile_pos = TilePos {
+            x: local.x as u32,
+            y: 0,
+        };
+        (key, tile_pos)
+    } else {
+        (chunk_of(p), local.into())
+    }
+}
+
+fn chunk_tilemap_size(hexagonal: bool) -> TilemapSize {
+    TilemapSize {
+        x: CHUNK_SIZE as u32,
+        y: if hexagonal { 1 } else { CHUNK_SIZE as u32 },
+    }
+}
+
+fn spawn_chunk_tilemap(
+    commands: &mut Commands,
+    tilemap_entity: Entity,
+    tile_storage: TileStorage,
+    texture: Handle<Image>,
+    chunk: Position,
+    hexagonal: bool,
+) {
+    // Position of the first cell of the tilemap
+    let origin = if hexagonal {
+        cell_to_world(
+            Position {
+                x: chunk.x * CHUNK_SIZE,
+                y: chunk.y,
+            },
+            Neighbourhood::Hexagonal,
+        )
+    } else {
+        cell_to_world(
+            Position {
+                x: chunk.x * CHUNK_SIZE,
+                y: chunk.y * CHUNK_SIZE,
+            },
+            Neighbourhood::Moore,
+        )
+    };
+
+    let tile_size = TilemapTileSize {
+        x: CELL_SIZE,
+        y: CELL_SIZE,
+    };
+
+    commands
+        .entity(tilemap_entity)
+        .insert_bundle(TilemapBundle {
+            grid_size: tile_size.into(),
+            size: chunk_tilemap_size(hexagonal),
+            storage: tile_storage,
+            texture: TilemapTexture::Single(texture),
+            tile_size,
+            map_type: TilemapType::Square {
+                diagonal_neighbors: true,
+            },
+            transform: Transform::from_translation(origin.extend(0.)),
+            ..default()
+        });
+}
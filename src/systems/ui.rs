@@ -1,12 +1,20 @@
 use bevy::prelude::*;
 
 use crate::{
-    components::{colors::Colors, line_assist::LineAssist, selection::Selection},
+    components::{
+        auto_route::AutoRoute, cell_state::CellState, colors::Colors, line_assist::LineAssist,
+        path_tool::PathTool, position::Position, selection::Selection,
+    },
     resources::{
         controls::{ControlMode, Controls},
         cursor::{Cursor, CursorMarker},
         gui::Gui,
+        speed::Speed,
+        status::Status,
+        stepping::Stepping,
     },
+    simulation::{grid::Grid, rule::Rule},
+    utils::cell_to_world,
     AppState, CELL_SIZE,
 };
 
@@ -14,41 +22,26 @@ use super::{
     camera::MousePosition,
     setup::{
         DragTextHorizontalMarker, DragTextVerticalMarker, LineAssistOverlayMarker, PauseIconMarker,
-        SelectionOverlayMarker,
+        SelectionOverlayMarker, StatsTextMarker, StatusTextMarker, WorldEdgeMarker,
+        WorldTextMarker,
     },
 };
 
 pub fn icon_toggles(
     app_state: Res<AppState>,
+    grid: Res<Grid>,
     cursor: Res<Cursor>,
     mouse_position: Res<MousePosition>,
     selection: Res<Selection>,
-    line_assist: Res<LineAssist>,
     controls: Res<Controls>,
     mut gui: ResMut<Gui>,
     mut pause_icon_query: Query<
         &mut Visibility,
-        (
-            With<PauseIconMarker>,
-            Without<SelectionOverlayMarker>,
-            Without<LineAssistOverlayMarker>,
-        ),
-    >,
-    mut line_assist_overlay_query: Query<
-        (&mut Transform, &mut Sprite, &mut Visibility),
-        (
-            With<LineAssistOverlayMarker>,
-            Without<SelectionOverlayMarker>,
-            Without<PauseIconMarker>,
-        ),
+        (With<PauseIconMarker>, Without<SelectionOverlayMarker>),
     >,
     mut selection_area_overlay_query: Query<
         (&mut Transform, &mut Sprite, &mut Visibility),
-        (
-            With<SelectionOverlayMarker>,
-            Without<LineAssistOverlayMarker>,
-            Without<PauseIconMarker>,
-        ),
+        (With<SelectionOverlayMarker>, Without<PauseIconMarker>),
     >,
     mut drag_overlay_text_query: Query<
         (&mut Text, Option<&DragTextHorizontalMarker>),
@@ -59,18 +52,7 @@ pub fn icon_toggles(
     let mut visibility = pause_icon_query.single_mut();
     visibility.is_visible = *app_state == AppState::Paused;
 
-    let (mut line_assist_transform, mut line_assist_sprite, mut line_assist_visibility) =
-        line_assist_overlay_query.single_mut();
-    if line_assist.drawing {
-        line_assist_sprite.color = Colors::SelectionOverlay;
-        line_assist_visibility.is_visible = true;
-
-        line_assist_transform.translation =
-            (Vec2::from(line_assist.area.a) - 0.5).extend(16.) * CELL_SIZE;
-        line_assist_sprite.custom_size = Some(Vec2::from(line_assist.area.size()) * CELL_SIZE);
-    } else {
-        line_assist_visibility.is_visible = false;
-    }
+    let neighbourhood = grid.topology().neighbourhood;
 
     let (mut selection_transform, mut selection_sprite, mut selection_visibility) =
         selection_area_overlay_query.single_mut();
@@ -78,8 +60,9 @@ pub fn icon_toggles(
         selection_sprite.color = Colors::SelectionOverlay;
         selection_visibility.is_visible = true;
 
-        selection_transform.translation =
-            (Vec2::from(selection.area.a) - 0.5).extend(16.) * CELL_SIZE;
+        selection_transform.translation = (cell_to_world(selection.area.a, neighbourhood)
+            - 0.5 * CELL_SIZE)
+            .extend(16. * CELL_SIZE);
         selection_sprite.custom_size = Some(Vec2::from(selection.area.size()) * CELL_SIZE);
     } else {
         selection_visibility.is_visible = false;
@@ -95,6 +78,58 @@ pub fn icon_toggles(
     //        }
 }
 
+/// Shows the cells of the line, path or route being drawn, colored by what the current mode does to
+/// them.
+pub fn update_line_preview(
+    mut commands: Commands,
+    line_assist: Res<LineAssist>,
+    path: Res<PathTool>,
+    auto_route: Res<AutoRoute>,
+    controls: Res<Controls>,
+    grid: Res<Grid>,
+    preview_query: Query<Entity, With<LineAssistOverlayMarker>>,
+) {
+    if !line_assist.is_changed()
+        && !path.is_changed()
+        && !auto_route.is_changed()
+        && !controls.is_changed()
+    {
+        return;
+    }
+
+    let entity = preview_query.single();
+    commands.entity(entity).despawn_descendants();
+    let (cells, mode) = if line_assist.drawing {
+        (&line_assist.cells, controls.mode)
+    } else if auto_route.active {
+        // Routes are always placed
+        (&auto_route.cells, ControlMode::Place)
+    } else {
+        (&path.cells, controls.mode)
+    };
+
+    let color = match mode {
+        ControlMode::Energize => Colors::DragOverlayEnergize,
+        ControlMode::Drain => Colors::DragOverlayDrain,
+        ControlMode::Delete => Colors::DragOverlayRemove,
+        _ => Colors::DragOverlayPlace,
+    };
+    let neighbourhood = grid.topology().neighbourhood;
+    commands.entity(entity).with_children(|parent| {
+        for p in cells.iter() {
+            parent.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(CELL_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(cell_to_world(*p, neighbourhood).extend(0.)),
+                ..default()
+            });
+        }
+    });
+}
+
 pub fn update_toolbar_icons(
     controls: Res<Controls>,
     mut gui: ResMut<Gui>,
@@ -119,12 +154,14 @@ pub fn update_toolbar_icons(
 
 pub fn update_cursor(
     mouse_position: Res<MousePosition>,
+    grid: Res<Grid>,
     gui: Res<Gui>,
     mut cursor_query: Query<(&mut Transform, &mut Sprite)>,
 ) {
     if mouse_position.is_changed() {
         let (mut transform, mut sprite) = cursor_query.get_mut(gui.cursor).unwrap();
-        transform.translation = (Vec2::from(mouse_position.cell) * CELL_SIZE).extend(2.);
+        transform.translation =
+            cell_to_world(mouse_position.cell, grid.topology().neighbourhood).extend(2.);
     }
 
     //    sprite.color = match controls.mode {
@@ -136,3 +173,124 @@ pub fn update_cursor(
     //        ControlMode::Move => Colors::Conductor,
     //    }
 }
+
+pub fn update_status(
+    status: Res<Status>,
+    mut status_text_query: Query<&mut Text, With<StatusTextMarker>>,
+) {
+    if status.is_changed() {
+        let mut text = status_text_query.single_mut();
+        text.sections[0].value = status.message.clone();
+        text.sections[0].style.color = if status.is_error {
+            Color::RED
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub fn update_world_text(
+    grid: Res<Grid>,
+    app_state: Res<AppState>,
+    stepping: Res<Stepping>,
+    speed: Res<Speed>,
+    mut world_text_query: Query<&mut Text, With<WorldTextMarker>>,
+) {
+    // The generation and measured speed are in the statistics
+    let world = format!("{}  {}", grid.rule().name(), grid.topology());
+    let value = if *app_state == AppState::Paused {
+        format!(
+            "{}  (N: step, Shift+N: advance {})",
+            world, stepping.advance_by
+        )
+    } else {
+        match speed.target() {
+            Some(target) => format!("{}  {} gen/s", world, target),
+            None => format!("{}  uncapped", world),
+        }
+    };
+
+    // Only touch the text when it changes, so it is not laid out again every frame
+    let mut text = world_text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+/// Shows the generation, how many cells are in each state, how fast the simulation runs, the
+/// cell under the cursor and the size of the selection.
+pub fn update_stats(
+    grid: Res<Grid>,
+    app_state: Res<AppState>,
+    speed: Res<Speed>,
+    mouse_position: Res<MousePosition>,
+    selection: Res<Selection>,
+    mut stats_text_query: Query<&mut Text, With<StatsTextMarker>>,
+) {
+    let running = *app_state == AppState::Running;
+    let per_second = |measured: f64| if running { measured.round() } else { 0. };
+
+    let mut lines = vec![format!("Generation {}", grid.generation())];
+    match grid.rule() {
+        Rule::Wireworld => {
+            lines.push(format!("Conductors {}", grid.count(CellState::Conductor)));
+            lines.push(format!("Heads {}", grid.count(CellState::Head)));
+            lines.push(format!("Tails {}", grid.count(CellState::Tail)));
+            lines.push(format!("Gen/s {}", per_second(speed.measured)));
+            lines.push(format!("Heads/s {}", per_second(speed.measured_heads)));
+        }
+        Rule::Generations { states, .. } => {
+            let dying: u64 = (2..states).map(|s| grid.count(CellState(s))).sum();
+            lines.push(format!("Alive {}", grid.count(CellState::Alive)));
+            lines.push(format!("Dying {}", dying));
+            lines.push(format!("Gen/s {}", per_second(speed.measured)));
+        }
+    }
+    let cell = mouse_position.cell;
+    lines.push(format!("Cursor {}, {}", cell.x, cell.y));
+    if selection.active || selection.selecting {
+        let size = selection.area.size();
+        lines.push(format!("Selection {} x {}", size.x, size.y));
+    } else {
+        lines.push("Selection none".to_string());
+    }
+
+    // Only touch the text when it changes, so it is not laid out again every frame
+    let value = lines.join("\n");
+    let mut text = stats_text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+/// Outlines bounded worlds, hexagonal ones are drawn as a parallelogram.
+pub fn update_world_edges(
+    grid: Res<Grid>,
+    mut edge_query: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<WorldEdgeMarker>>,
+) {
+    if !grid.is_changed() {
+        return;
+    }
+
+    let topology = grid.topology();
+    let corner = |x: i32, y: i32, outwards: Vec2| {
+        let p = topology.origin + Position::from((x, y));
+        cell_to_world(p, topology.neighbourhood) + outwards * 0.5 * CELL_SIZE
+    };
+    let (w, h) = (topology.width - 1, topology.height - 1);
+    let corners = [
+        corner(0, 0, Vec2::new(-1., -1.)),
+        corner(w, 0, Vec2::new(1., -1.)),
+        corner(w, h, Vec2::new(1., 1.)),
+        corner(0, h, Vec2::new(-1., 1.)),
+    ];
+
+    for (i, (mut transform, mut sprite, mut visibility)) in edge_query.iter_mut().enumerate() {
+        visibility.is_visible = !topology.is_infinite();
+        let (from, to) = (corners[i], corners[(i + 1) % 4]);
+        let edge = to - from;
+        transform.translation = ((from + to) / 2.).extend(16. * CELL_SIZE);
+        transform.rotation = Quat::from_rotation_z(edge.y.atan2(edge.x));
+        sprite.custom_size = Some(Vec2::new(edge.length(), CELL_SIZE / 2.));
+    }
+}
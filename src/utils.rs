@@ -1,5 +1,34 @@
 use std::ops::{Rem, Sub};
 
+use bevy::prelude::Vec2;
+
+use crate::{components::position::Position, simulation::topology::Neighbourhood, CELL_SIZE};
+
 pub fn closest_multiple<T: Copy + Sub<T, Output = T> + Rem<f32, Output = T>>(e: T, i: f32) -> T {
     e - (e % i)
 }
+
+/// Centre of the cell at `p` in world space.
+///
+/// Hexagonal worlds are drawn as a brick wall, every row shifted half a cell to the right of
+/// the one below, which makes each cell touch exactly its six neighbours.
+pub fn cell_to_world(p: Position, neighbourhood: Neighbourhood) -> Vec2 {
+    let shift = match neighbourhood {
+        Neighbourhood::Hexagonal => p.y as f32 * 0.5,
+        _ => 0.,
+    };
+    Vec2::new(p.x as f32 + shift, p.y as f32) * CELL_SIZE
+}
+
+/// The cell drawn at `world`, the inverse of [`cell_to_world`].
+pub fn world_to_cell(world: Vec2, neighbourhood: Neighbourhood) -> Position {
+    let y = (world.y / CELL_SIZE).round();
+    let shift = match neighbourhood {
+        Neighbourhood::Hexagonal => y * 0.5,
+        _ => 0.,
+    };
+    Position {
+        x: (world.x / CELL_SIZE - shift).round() as i32,
+        y: y as i32,
+    }
+}